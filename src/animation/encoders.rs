@@ -0,0 +1,233 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use image::{Rgb, RgbImage};
+use rayon::prelude::*;
+
+use crate::render::{ColorMap, InteriorColoring};
+
+/// File format that holds every frame of an animation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Container {
+    Gif,
+    Apng,
+}
+
+impl Container {
+    /// Infer the container from the extension of a path
+    pub(crate) fn from_path(path: &Path) -> Option<Container> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Some(Container::Gif),
+            "png" | "apng" => Some(Container::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// 256-color table for indexed frames, made up of the color of points that 
+/// don't escape followed by evenly spaced samples of a palette in palette 
+/// order, so that quantized frames keep the palette's gradient. If points 
+/// that don't escape are colored from a palette of their own, the samples 
+/// are split between the two palettes.
+pub(crate) struct ColorTable {
+    colors: Vec<Rgb<u8>>,
+}
+
+impl ColorTable {
+    const SIZE: usize = 256;
+
+    pub(crate) fn new(color_map: &ColorMap) -> ColorTable {
+        let interior_color = color_map.interior_color.as_sRGB().as_image_Rgb();
+        let (num_exterior, num_interior) = match color_map.interior {
+            InteriorColoring::Solid => (ColorTable::SIZE - 1, 0),
+            _ => (ColorTable::SIZE / 2, ColorTable::SIZE / 2 - 1),
+        };
+        let last = (num_exterior - 1) as f64;
+        let exterior = (0..num_exterior).map(|k| {
+            let scalar = k as f64 / last;
+            color_map.palette.map_scalar_to_color(scalar, color_map.reverse).as_image_Rgb()
+        });
+        let last = num_interior.saturating_sub(1) as f64;
+        let interior = (0..num_interior).map(|k| {
+            color_map.interior_palette.map_scalar_to_color(k as f64 / last, false).as_image_Rgb()
+        });
+        let colors = std::iter::once(interior_color).chain(exterior).chain(interior).collect();
+        ColorTable { colors }
+    }
+
+    /// Return the index of the color nearest to `color` in sRGB, preferring 
+    /// the earliest color in case of a tie
+    fn index_of(&self, color: &Rgb<u8>) -> u8 {
+        let distance = |other: &Rgb<u8>| -> u32 {
+            color
+                .0
+                .iter()
+                .zip(other.0.iter())
+                .map(|(a, b)| (a.abs_diff(*b) as u32).pow(2))
+                .sum()
+        };
+        let (index, _) = self
+            .colors
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, other)| distance(other))
+            .expect("the color table isn't empty");
+        index as u8
+    }
+
+    fn indices(&self, image: &RgbImage) -> Vec<u8> {
+        image
+            .as_raw()
+            .par_chunks(3)
+            .map(|p| self.index_of(&Rgb([p[0], p[1], p[2]])))
+            .collect()
+    }
+
+    fn as_bytes(&self) -> Vec<u8> {
+        self.colors.iter().flat_map(|c| c.0).collect()
+    }
+}
+
+/// Writer of an animation to a single looping file, one frame at a time
+pub(crate) enum AnimationEncoder {
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        color_table: ColorTable,
+        /// Delay between frames in hundredths of a second
+        delay: u16,
+    },
+    Apng(png::Writer<BufWriter<File>>),
+}
+
+impl AnimationEncoder {
+    /// Create a file to hold `num_frames` frames shown at `fps` frames per 
+    /// second. GIF frames are quantized to colors of the palette.
+    pub(crate) fn new(
+        container: Container,
+        out_path: &Path,
+        image_dims: (u32, u32),
+        num_frames: u32,
+        fps: u16,
+        color_table: ColorTable,
+    ) -> Result<AnimationEncoder> {
+        let writer = BufWriter::new(File::create(out_path)?);
+        match container {
+            Container::Gif => {
+                let (width, height) = match (u16::try_from(image_dims.0), u16::try_from(image_dims.1)) {
+                    (Ok(width), Ok(height)) => (width, height),
+                    _ => bail!("GIF images can't be wider or taller than {} pixels", u16::MAX),
+                };
+                let mut encoder = gif::Encoder::new(writer, width, height, &color_table.as_bytes())?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+                let delay = (100.0 / fps as f64).round().max(1.0) as u16;
+                Ok(AnimationEncoder::Gif { encoder, color_table, delay })
+            }
+            Container::Apng => {
+                let mut encoder = png::Encoder::new(writer, image_dims.0, image_dims.1);
+                encoder.set_color(png::ColorType::Rgb);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_animated(num_frames, 0)?;
+                encoder.set_frame_delay(1, fps)?;
+                Ok(AnimationEncoder::Apng(encoder.write_header()?))
+            }
+        }
+    }
+
+    pub(crate) fn write_frame(&mut self, image: &RgbImage) -> Result<()> {
+        match self {
+            AnimationEncoder::Gif { encoder, color_table, delay } => {
+                let indices = color_table.indices(image);
+                let (width, height) = (image.width() as u16, image.height() as u16);
+                let mut frame = gif::Frame::from_indexed_pixels(width, height, &indices, None);
+                frame.delay = *delay;
+                encoder.write_frame(&frame)?;
+            }
+            AnimationEncoder::Apng(writer) => writer.write_image_data(image.as_raw())?,
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> Result<()> {
+        match self {
+            AnimationEncoder::Gif { encoder, .. } => drop(encoder.into_inner()?),
+            AnimationEncoder::Apng(writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use image::Rgb;
+
+    use crate::animation::encoders::{ColorTable, Container};
+    use crate::color::palettes::{PolarLuvPalette, Wrap};
+    use crate::color::PolarLuv;
+    use crate::render::{ColorMap, Coloring, InteriorColoring, Mapping, Transparency};
+
+    #[test]
+    fn container_from_path_test() {
+        assert_eq!(Container::from_path(Path::new("zoom.gif")), Some(Container::Gif));
+        assert_eq!(Container::from_path(Path::new("zoom.GIF")), Some(Container::Gif));
+        assert_eq!(Container::from_path(Path::new("zoom.png")), Some(Container::Apng));
+        assert_eq!(Container::from_path(Path::new("zoom.apng")), Some(Container::Apng));
+        assert_eq!(Container::from_path(Path::new("zoom.mp4")), None);
+        assert_eq!(Container::from_path(Path::new("zoom")), None);
+    }
+
+    #[test]
+    fn color_table_index_of_test() {
+        let color_table = ColorTable {
+            colors: vec![Rgb([0, 0, 0]), Rgb([100, 0, 0]), Rgb([200, 0, 0]), Rgb([200, 0, 0])],
+        };
+        assert_eq!(color_table.index_of(&Rgb([0, 0, 0])), 0);
+        assert_eq!(color_table.index_of(&Rgb([40, 10, 10])), 0);
+        assert_eq!(color_table.index_of(&Rgb([60, 0, 0])), 1);
+        assert_eq!(color_table.index_of(&Rgb([255, 0, 0])), 2);
+        assert_eq!(color_table.as_bytes().len(), 12);
+    }
+
+    #[test]
+    fn color_table_new_test() {
+        let palette = PolarLuvPalette::new(Path::new("assets/palettes/Lajolla.json")).unwrap();
+        let interior_palette =
+            PolarLuvPalette::new(Path::new("assets/palettes/Mako.json")).unwrap();
+        let interior_color: PolarLuv = "120,30,90".parse().unwrap();
+        let color_map = ColorMap {
+            palette: &palette,
+            reverse: true,
+            coloring: Coloring::EscapeTime,
+            mapping: Mapping::Linear,
+            exponent: 1.0,
+            escape_range: (0.0, None),
+            thickness: 1.0,
+            wrap: Wrap::Clamp,
+            repeats: 1.0,
+            offset: 0.0,
+            interior: InteriorColoring::Solid,
+            interior_palette: &interior_palette,
+            interior_color: interior_color.as_RGB(),
+            transparency: Transparency::default(),
+        };
+
+        let color_table = ColorTable::new(&color_map);
+        assert_eq!(color_table.colors.len(), 256);
+        assert_eq!(color_table.colors[0], interior_color.as_image_Rgb());
+        assert_eq!(color_table.colors[1], palette.map_scalar_to_color(0.0, true).as_image_Rgb());
+        assert_eq!(color_table.colors[255], palette.map_scalar_to_color(1.0, true).as_image_Rgb());
+
+        // An interior palette gets half of the table
+        let color_map = ColorMap { interior: InteriorColoring::Period, ..color_map };
+        let color_table = ColorTable::new(&color_map);
+        assert_eq!(color_table.colors.len(), 256);
+        assert_eq!(color_table.colors[128], palette.map_scalar_to_color(1.0, true).as_image_Rgb());
+        let interior_color = |scalar| interior_palette.map_scalar_to_color(scalar, false).as_image_Rgb();
+        assert_eq!(color_table.colors[129], interior_color(0.0));
+        assert_eq!(color_table.colors[255], interior_color(1.0));
+    }
+}
@@ -0,0 +1,147 @@
+pub(crate) mod encoders;
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use clap::ValueEnum;
+use num::Complex;
+use serde::{de, Deserialize, Deserializer};
+
+/// Timing function that shapes the progress between two keyframes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Easing {
+    /// Constant speed
+    Linear,
+    /// Start slowly and speed up
+    EaseIn,
+    /// Start quickly and slow down
+    EaseOut,
+    /// Start and end slowly (smoothstep)
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map progress in [0.0, 1.0] to eased progress in [0.0, 1.0]
+    pub(crate) fn apply(&self, s: f64) -> f64 {
+        match self {
+            Easing::Linear => s,
+            Easing::EaseIn => s * s,
+            Easing::EaseOut => s * (2.0 - s),
+            Easing::EaseInOut => s * s * (3.0 - 2.0 * s),
+        }
+    }
+}
+
+/// A view of the complex plane at a moment in an animation
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub(crate) struct Keyframe {
+    #[serde(deserialize_with = "deserialize_complex")]
+    pub(crate) center: Complex<f64>,
+    pub(crate) zoom: f64,
+}
+
+fn deserialize_complex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Complex<f64>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(|_| de::Error::custom(format!("invalid complex number `{}`", s)))
+}
+
+impl Keyframe {
+    /// Load a list of keyframes from a JSON file
+    pub(crate) fn load(keyframes_path: &Path) -> Result<Vec<Keyframe>, io::Error> {
+        let keyframes_file = File::open(keyframes_path)?;
+        let keyframes_reader = BufReader::new(keyframes_file);
+        let keyframes: Vec<Keyframe> = serde_json::from_reader(keyframes_reader)?;
+        Ok(keyframes)
+    }
+
+    /// Interpolate between this keyframe and the next. The zoom changes 
+    /// exponentially, so that it appears to change at a steady rate, and the 
+    /// center moves in proportion to the change in the view's radius, so 
+    /// that a zoom into a point off center stays fixed on that point.
+    fn interpolate(&self, next: &Keyframe, s: f64) -> Keyframe {
+        if s >= 1.0 {
+            return *next;
+        }
+        let zoom = (self.zoom.ln() + (next.zoom.ln() - self.zoom.ln()) * s).exp();
+        let (radius, start, end) = (zoom.recip(), self.zoom.recip(), next.zoom.recip());
+        let w = if ((end - start) / start).abs() > 1e-9 {
+            (radius - start) / (end - start)
+        } else {
+            s
+        };
+        Keyframe { center: self.center + (next.center - self.center) * w, zoom }
+    }
+}
+
+/// Return the view at time `t` in [0.0, 1.0] of an animation that spends an 
+/// equal amount of time between each pair of consecutive keyframes
+pub(crate) fn interpolate(keyframes: &[Keyframe], t: f64, easing: Easing) -> Keyframe {
+    let num_segments = keyframes.len() - 1;
+    if num_segments == 0 {
+        return keyframes[0];
+    }
+    let position = t.clamp(0.0, 1.0) * num_segments as f64;
+    let segment = (position.floor() as usize).min(num_segments - 1);
+    let s = easing.apply(position - segment as f64);
+    keyframes[segment].interpolate(&keyframes[segment + 1], s)
+}
+
+#[cfg(test)]
+mod tests {
+    use num::Complex;
+
+    use crate::animation::{interpolate, Easing, Keyframe};
+
+    #[test]
+    fn easing_test() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+        }
+        assert_eq!(Easing::EaseIn.apply(0.5), 0.25);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.75);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+        assert!(Easing::EaseInOut.apply(0.1) < 0.1);
+    }
+
+    #[test]
+    fn interpolate_test() {
+        let keyframes = [
+            Keyframe { center: Complex::new(0.0, 0.0), zoom: 1.0 },
+            Keyframe { center: Complex::new(-1.0, 0.0), zoom: 100.0 },
+            Keyframe { center: Complex::new(-1.0, 0.0), zoom: 100.0 },
+        ];
+        let approx_eq = |a: Keyframe, b: Keyframe| {
+            (a.center - b.center).norm() < 1e-12 && (a.zoom / b.zoom - 1.0).abs() < 1e-12
+        };
+
+        assert!(approx_eq(interpolate(&keyframes, 0.0, Easing::Linear), keyframes[0]));
+        assert!(approx_eq(interpolate(&keyframes, 0.5, Easing::Linear), keyframes[1]));
+        assert!(approx_eq(interpolate(&keyframes, 1.0, Easing::Linear), keyframes[2]));
+
+        // Halfway through the first segment, the zoom is the geometric mean 
+        // and the view has covered most of the distance to the new center
+        let halfway = interpolate(&keyframes, 0.25, Easing::Linear);
+        assert!((halfway.zoom - 10.0).abs() < 1e-12);
+        assert!((halfway.center.re + 10.0 / 11.0).abs() < 1e-12);
+
+        // Without a change in zoom, the center moves linearly
+        let keyframes = [
+            Keyframe { center: Complex::new(0.0, 0.0), zoom: 2.0 },
+            Keyframe { center: Complex::new(0.0, 1.0), zoom: 2.0 },
+        ];
+        let quarter = interpolate(&keyframes, 0.25, Easing::Linear);
+        assert!(approx_eq(quarter, Keyframe { center: Complex::new(0.0, 0.25), zoom: 2.0 }));
+    }
+
+    #[test]
+    fn keyframe_deserialize_test() {
+        let json = r#"[{ "center": "-0.75 + 0.1i", "zoom": 4 }]"#;
+        let keyframes: Vec<Keyframe> = serde_json::from_str(json).unwrap();
+        assert_eq!(keyframes, [Keyframe { center: Complex::new(-0.75, 0.1), zoom: 4.0 }]);
+
+        let json = r#"[{ "center": "nowhere", "zoom": 4 }]"#;
+        assert!(serde_json::from_str::<Vec<Keyframe>>(json).is_err());
+    }
+}
@@ -3,13 +3,15 @@
 
 pub(crate) mod palettes;
 
+use std::str::FromStr;
+
 use float_cmp::{ApproxEq, F64Margin};
 use serde::Deserialize;
 
 pub(crate) static MARGIN: F64Margin = F64Margin { epsilon: 0.0, ulps: 1 };
 
 /// Cylindrical transformation of CIELUV (HCL or CIELCh(uv) color space)
-#[derive(Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub(crate) struct PolarLuv {
     h: f64,
     C: f64,
@@ -25,15 +27,43 @@ impl PolarLuv {
         }
     }
 
-    pub(crate) fn as_image_Rgb(&self) -> image::Rgb<u8> {
+    pub(crate) fn as_RGB(&self) -> RGB {
         self.as_Luv()
             .as_XYZ()
             .as_RGB()
+    }
+
+    pub(crate) fn as_image_Rgb(&self) -> image::Rgb<u8> {
+        self.as_RGB()
             .as_sRGB()
             .as_image_Rgb()
     }
 }
 
+impl FromStr for PolarLuv {
+    type Err = String;
+
+    /// Parse a color written as its hue, chroma and luminance separated by 
+    /// commas, e.g., `300,40,15`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let components = s
+            .split(',')
+            .map(|component| component.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid HCL color `{}`", s))?;
+        let is_valid = |h: f64, C: f64, L: f64| {
+            h.is_finite() && C >= 0.0 && C.is_finite() && (0.0..=100.0).contains(&L)
+        };
+        match components[..] {
+            [h, C, L] if is_valid(h, C, L) => Ok(PolarLuv { h, C, L }),
+            _ => Err(format!(
+                "expected a hue, a chroma of at least 0 and a luminance from 0 to 100 separated by commas, got `{}`",
+                s
+            )),
+        }
+    }
+}
+
 /// CIE 1976 L*, u*, v* color space (CIELUV)
 pub(crate) struct Luv {
    L: f64,
@@ -83,6 +113,7 @@ impl XYZ {
 }
 
 /// Rec. 709 standard for RGB color model
+#[derive(Clone, Copy)]
 pub(crate) struct RGB {
     R: f64,
     G: f64,
@@ -90,6 +121,17 @@ pub(crate) struct RGB {
 }
 
 impl RGB {
+    pub(crate) const BLACK: RGB = RGB { R: 0.0, G: 0.0, B: 0.0 };
+
+    /// Average colors in linear light, as when mixing light physically
+    pub(crate) fn mean<I: IntoIterator<Item = RGB>>(colors: I) -> RGB {
+        let (n, R, G, B) = colors
+            .into_iter()
+            .fold((0, 0.0, 0.0, 0.0), |(n, R, G, B), c| (n + 1, R + c.R, G + c.G, B + c.B));
+        let n = n as f64;
+        RGB { R: R / n, G: G / n, B: B / n }
+    }
+
     pub(crate) fn as_sRGB(&self) -> sRGB {
         sRGB {
             R: sRGB::transfer_function(self.R),
@@ -97,6 +139,12 @@ impl RGB {
             B: sRGB::transfer_function(self.B)
         }
     }
+
+    /// Convert to floating-point components in linear light without 
+    /// clipping them to the gamut, as scene data for compositing
+    pub(crate) fn as_image_Rgb32F(&self) -> image::Rgb<f32> {
+        image::Rgb([self.R as f32, self.G as f32, self.B as f32])
+    }
 }
 
 /// sRGB standard as defined in IEC 61966-2-1:1999
@@ -117,13 +165,7 @@ impl sRGB {
 
     pub(crate) fn as_image_Rgb(&self) -> image::Rgb<u8> {
         fn confine_component_to_gamut(component: f64) -> f64 {
-            if component < 0.0 {
-                0.0
-            } else if component > 255.0 {
-                255.0
-            } else {
-                component
-            }
+            component.clamp(0.0, 255.0)
         }
 
         image::Rgb([
@@ -132,6 +174,18 @@ impl sRGB {
             confine_component_to_gamut(self.B * 255.0) as u8
         ])
     }
+
+    pub(crate) fn as_image_Rgb16(&self) -> image::Rgb<u16> {
+        fn confine_component_to_gamut(component: f64) -> f64 {
+            component.clamp(0.0, 65535.0)
+        }
+
+        image::Rgb([
+            confine_component_to_gamut(self.R * 65535.0) as u16,
+            confine_component_to_gamut(self.G * 65535.0) as u16,
+            confine_component_to_gamut(self.B * 65535.0) as u16
+        ])
+    }
 }
 
 #[cfg(test)]
@@ -145,16 +199,32 @@ mod tests {
 
     static N_SIG_FIGS: u32 = 5;
 
-    #[test]
-    fn PolarLuv_as_Luv_test() {
-        impl Luv {
-            fn approx_eq(&self, other: &Luv) -> bool {
-                sf_eq(self.L, other.L, N_SIG_FIGS)
-                    && sf_eq(self.u, other.u, N_SIG_FIGS)
-                    && sf_eq(self.v, other.v, N_SIG_FIGS)
-            }
+    impl Luv {
+        fn approx_eq(&self, other: &Luv) -> bool {
+            sf_eq(self.L, other.L, N_SIG_FIGS)
+                && sf_eq(self.u, other.u, N_SIG_FIGS)
+                && sf_eq(self.v, other.v, N_SIG_FIGS)
         }
+    }
 
+    impl XYZ {
+        fn approx_eq(&self, other: &XYZ) -> bool {
+            sf_eq(self.X, other.X, N_SIG_FIGS)
+                && sf_eq(self.Y, other.Y, N_SIG_FIGS)
+                && sf_eq(self.Z, other.Z, N_SIG_FIGS)
+        }
+    }
+
+    impl RGB {
+        fn approx_eq(&self, other: &RGB) -> bool {
+            dp_eq(self.R, other.R, N_SIG_FIGS)
+                && dp_eq(self.G, other.G, N_SIG_FIGS)
+                && dp_eq(self.B, other.B, N_SIG_FIGS)
+        }
+    }
+
+    #[test]
+    fn PolarLuv_as_Luv_test() {
         let point1 = PolarLuv { h: 0.0, C: 0.0, L: 0.0 }; // black
         assert!(point1.as_Luv().approx_eq(&Luv { L: 0.0, u: 0.0, v: 0.0 }));
 
@@ -164,14 +234,6 @@ mod tests {
 
     #[test]
     fn Luv_as_XYZ_test() {
-        impl XYZ {
-            fn approx_eq(&self, other: &XYZ) -> bool {
-                sf_eq(self.X, other.X, N_SIG_FIGS)
-                    && sf_eq(self.Y, other.Y, N_SIG_FIGS)
-                    && sf_eq(self.Z, other.Z, N_SIG_FIGS)
-            }
-        }
-
         let point1 = Luv { L: 0.0, u: 0.0, v: 0.0 };
         assert!(point1.as_XYZ().approx_eq(&XYZ { X: 0.0, Y: 0.0, Z: 0.0 }));
 
@@ -181,14 +243,6 @@ mod tests {
 
     #[test]
     fn XYZ_as_RGB_test() {
-        impl RGB {
-            fn approx_eq(&self, other: &RGB) -> bool {
-                dp_eq(self.R, other.R, N_SIG_FIGS)
-                    && dp_eq(self.G, other.G, N_SIG_FIGS)
-                    && dp_eq(self.B, other.B, N_SIG_FIGS)
-            }
-        }
-
         let point1 = XYZ { X: 0.0, Y: 0.0, Z: 0.0 };
         assert!(point1.as_RGB().approx_eq(&RGB { R: 0.0, G: 0.0, B: 0.0 }));
 
@@ -196,6 +250,19 @@ mod tests {
         assert!(point2.as_RGB().approx_eq(&RGB { R: 0.07121_7, G: -0.00360_3, B: 0.09029_9 }));
     }
 
+    #[test]
+    fn RGB_mean_test() {
+        let colors = [
+            RGB { R: 1.0, G: 0.0, B: 0.5 },
+            RGB::BLACK,
+        ];
+        assert!(RGB::mean(colors).approx_eq(&RGB { R: 0.5, G: 0.0, B: 0.25 }));
+
+        // A 50% mix of black and white is brighter than sRGB value 0.5
+        let gray = RGB::mean([RGB { R: 1.0, G: 1.0, B: 1.0 }, RGB::BLACK]);
+        assert_eq!(gray.as_sRGB().as_image_Rgb(), image::Rgb([187; 3]));
+    }
+
     #[test]
     fn sRGB_transfer_function_test() {
         assert!(sRGB::transfer_function(0.0).approx_eq(0.0, MARGIN));
@@ -211,4 +278,42 @@ mod tests {
         let point2 = PolarLuv { h: 300.0, C: 40.0, L: 15.0 };
         assert_eq!(point2.as_image_Rgb(), image::Rgb([75, 0, 84]));
     }
+
+    #[test]
+    fn RGB_as_image_Rgb32F_test() {
+        let point1 = RGB { R: 0.25, G: 0.5, B: 1.0 };
+        assert_eq!(point1.as_image_Rgb32F(), image::Rgb([0.25, 0.5, 1.0]));
+
+        // Components outside the gamut are kept
+        let point2 = PolarLuv { h: 300.0, C: 40.0, L: 15.0 }.as_RGB();
+        assert!(point2.as_image_Rgb32F().0[1] < 0.0);
+        assert_eq!(point2.as_sRGB().as_image_Rgb().0[1], 0);
+    }
+
+    #[test]
+    fn sRGB_as_image_Rgb16_test() {
+        let point1 = sRGB { R: 0.5, G: 0.5, B: 1.0 };
+        assert_eq!(point1.as_image_Rgb16(), image::Rgb([32767, 32767, 65535]));
+
+        // Components outside the gamut are clipped
+        let point2 = sRGB { R: -0.1, G: 1.2, B: 0.25 };
+        assert_eq!(point2.as_image_Rgb16(), image::Rgb([0, 65535, 16383]));
+
+        // Colors that differ by less than an 8-bit step stay distinct
+        let point3 = sRGB { R: 0.501, G: 0.5, B: 1.0 };
+        assert_eq!(point3.as_image_Rgb(), point1.as_image_Rgb());
+        assert_eq!(point3.as_image_Rgb16(), image::Rgb([32833, 32767, 65535]));
+    }
+
+    #[test]
+    fn PolarLuv_from_str_test() {
+        let point = "300, 40, 15".parse::<PolarLuv>().unwrap();
+        assert_eq!(point.as_image_Rgb(), image::Rgb([75, 0, 84]));
+
+        assert!("300,40".parse::<PolarLuv>().is_err());
+        assert!("300,40,15,0".parse::<PolarLuv>().is_err());
+        assert!("300,-40,15".parse::<PolarLuv>().is_err());
+        assert!("300,40,101".parse::<PolarLuv>().is_err());
+        assert!("hue,40,15".parse::<PolarLuv>().is_err());
+    }
 }
@@ -2,11 +2,45 @@ use std::fs::File;
 use std::io::{self, BufReader};
 use std::path::Path;
 
+use clap::ValueEnum;
 use float_cmp::ApproxEq;
 use serde::Deserialize;
 
 use crate::color::{PolarLuv, MARGIN};
 
+/// Strategy for bringing palette positions outside [0.0, 1.0] back into range
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Wrap {
+    /// Saturate at either end of the palette
+    Clamp,
+    /// Start over from the beginning of the palette
+    Repeat,
+    /// Run back and forth through the palette
+    Mirror,
+}
+
+impl Wrap {
+    pub(crate) fn apply(&self, scalar: f64) -> f64 {
+        match self {
+            Wrap::Clamp => scalar.clamp(0.0, 1.0),
+            Wrap::Repeat => scalar.rem_euclid(1.0),
+            Wrap::Mirror => {
+                let t = scalar.rem_euclid(2.0);
+                if t > 1.0 { 2.0 - t } else { t }
+            }
+        }
+    }
+
+    /// Return the shift in palette position after which colors repeat, if any
+    pub(crate) fn period(&self) -> Option<f64> {
+        match self {
+            Wrap::Clamp => None,
+            Wrap::Repeat => Some(1.0),
+            Wrap::Mirror => Some(2.0),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub(crate) struct PolarLuvPalette {
     start: PolarLuv,
@@ -72,3 +106,30 @@ impl PolarLuvPalette {
         PolarLuv { h, C, L }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::color::palettes::Wrap;
+
+    #[test]
+    fn wrap_test() {
+        for wrap in [Wrap::Clamp, Wrap::Repeat, Wrap::Mirror] {
+            assert_eq!(wrap.apply(0.0), 0.0);
+            assert_eq!(wrap.apply(0.25), 0.25);
+        }
+        assert_eq!(Wrap::Clamp.apply(1.25), 1.0);
+        assert_eq!(Wrap::Clamp.apply(-0.25), 0.0);
+        assert_eq!(Wrap::Repeat.apply(1.25), 0.25);
+        assert_eq!(Wrap::Repeat.apply(-0.25), 0.75);
+        assert_eq!(Wrap::Mirror.apply(1.25), 0.75);
+        assert_eq!(Wrap::Mirror.apply(2.25), 0.25);
+        assert_eq!(Wrap::Mirror.apply(-0.25), 0.25);
+
+        // Shifting by a period leaves the palette position unchanged
+        for wrap in [Wrap::Repeat, Wrap::Mirror] {
+            let period = wrap.period().unwrap();
+            assert_eq!(wrap.apply(0.375 + period), wrap.apply(0.375));
+        }
+        assert_eq!(Wrap::Clamp.period(), None);
+    }
+}
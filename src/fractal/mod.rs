@@ -0,0 +1,617 @@
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use num::Complex;
+
+use crate::perturbation::{BigComplex, ReferenceOrbit};
+
+/// The exponent `d` in the Multibrot formula z ↦ zᵈ + c
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Exponent {
+    Integer(u32),
+    Real(f64),
+}
+
+impl Exponent {
+    /// Raise `z` to this power, multiplying repeatedly if the power is an 
+    /// integer and falling back on the general complex power otherwise
+    pub(crate) fn raise(&self, z: Complex<f64>) -> Complex<f64> {
+        match *self {
+            Exponent::Integer(d) => z.powu(d),
+            Exponent::Real(d) => z.powf(d),
+        }
+    }
+
+    /// Return the derivative d·z^(d - 1) of z ↦ zᵈ at `z`
+    pub(crate) fn differentiate(&self, z: Complex<f64>) -> Complex<f64> {
+        match *self {
+            Exponent::Integer(d) => z.powu(d - 1) * d as f64,
+            Exponent::Real(d) => z.powf(d - 1.0) * d,
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> f64 {
+        match *self {
+            Exponent::Integer(d) => d as f64,
+            Exponent::Real(d) => d,
+        }
+    }
+}
+
+impl FromStr for Exponent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let d: f64 = s.trim().parse().map_err(|_| format!("invalid exponent `{}`", s))?;
+        if !(d > 1.0 && d.is_finite()) {
+            return Err(format!("exponent must be a finite number greater than 1, got {}", d));
+        }
+        if d.fract() == 0.0 && d <= u32::MAX as f64 {
+            Ok(Exponent::Integer(d as u32))
+        } else {
+            Ok(Exponent::Real(d))
+        }
+    }
+}
+
+/// An escape-time formula of the form z ↦ f(z) + c
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Formula {
+    /// z² + c
+    Mandelbrot,
+    /// zᵈ + c
+    Multibrot,
+    /// (|Re z| + i|Im z|)² + c
+    BurningShip,
+    /// conj(z)² + c
+    Tricorn,
+    /// |Re z²| + i Im z² + c
+    Celtic,
+}
+
+impl Formula {
+    /// The default exponent of the Multibrot formula
+    pub(crate) const MULTIBROT_POWER: Exponent = Exponent::Integer(3);
+
+    /// Return the degree of the formula as a polynomial in z
+    pub(crate) fn degree(&self, power: Exponent) -> f64 {
+        match self {
+            Formula::Multibrot => power.as_f64(),
+            _ => 2.0,
+        }
+    }
+
+    /// Return whether the formula is holomorphic in z, i.e., whether orbits 
+    /// have a complex derivative that can be used for distance estimation
+    pub(crate) fn is_holomorphic(&self) -> bool {
+        matches!(self, Formula::Mandelbrot | Formula::Multibrot)
+    }
+
+    /// Return the derivative of a holomorphic formula with respect to z
+    fn differentiate(&self, z: Complex<f64>, power: Exponent) -> Complex<f64> {
+        match self {
+            Formula::Multibrot => power.differentiate(z),
+            _ => z * 2.0,
+        }
+    }
+
+    /// Apply the formula once to `z`; `power` is used only by the Multibrot 
+    /// formula. The Mandelbrot formula squares z with the general complex 
+    /// power, as earlier versions of Fraczal did, which rounds differently 
+    /// from multiplication; this keeps their renders unchanged.
+    pub(crate) fn step(&self, z: Complex<f64>, c: Complex<f64>, power: Exponent) -> Complex<f64> {
+        match self {
+            Formula::Mandelbrot => z.powf(2.0) + c,
+            Formula::Multibrot => power.raise(z) + c,
+            Formula::BurningShip => {
+                let w = Complex::new(z.re.abs(), z.im.abs());
+                w * w + c
+            }
+            Formula::Tricorn => {
+                let w = z.conj();
+                w * w + c
+            }
+            Formula::Celtic => {
+                let w = z * z;
+                Complex::new(w.re.abs(), w.im) + c
+            }
+        }
+    }
+}
+
+/// The state of an orbit when it escaped
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Escape {
+    /// Number of iterations taken to escape (the "escape time")
+    pub(crate) iter: usize,
+    /// Modulus of z at escape
+    pub(crate) abs_z: f64,
+    /// Modulus of the derivative of z with respect to the point being 
+    /// iterated, if tracked
+    pub(crate) abs_dz: f64,
+}
+
+impl Escape {
+    pub(crate) fn new(iter: usize, z: Complex<f64>, dz: Complex<f64>) -> Escape {
+        Escape { iter, abs_z: z.norm(), abs_dz: dz.norm() }
+    }
+}
+
+/// The state of an orbit that stayed bounded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Interior {
+    /// Modulus of z after the last iteration
+    pub(crate) abs_z: f64,
+    /// Smallest modulus of z along the orbit, not counting its starting 
+    /// point
+    pub(crate) min_abs_z: f64,
+    /// Period of the cycle the orbit was found to settle into, or 0 if none 
+    /// was found
+    pub(crate) period: usize,
+}
+
+impl Interior {
+    /// The orbit of a point recognized as interior without iterating, whose 
+    /// moduli are unknown and recorded as 0
+    pub(crate) fn of_period(period: usize) -> Interior {
+        Interior { abs_z: 0.0, min_abs_z: 0.0, period }
+    }
+
+    pub(crate) fn new(z: Complex<f64>, min_norm_sqr: f64, period: usize) -> Interior {
+        let norm_sqr = z.norm_sqr();
+        Interior { abs_z: norm_sqr.sqrt(), min_abs_z: min_norm_sqr.min(norm_sqr).sqrt(), period }
+    }
+}
+
+/// The outcome of iterating a point
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Orbit {
+    Escaped(Escape),
+    Bounded(Interior),
+}
+
+impl Orbit {
+    /// Return the escape of an orbit that escaped
+    pub(crate) fn escape(&self) -> Option<Escape> {
+        match *self {
+            Orbit::Escaped(escape) => Some(escape),
+            Orbit::Bounded(_) => None,
+        }
+    }
+}
+
+/// A formula together with the plane in which it's drawn: the parameter plane 
+/// (Mandelbrot-like sets) or, if a constant is given, the dynamical plane 
+/// (Julia sets)
+#[derive(Clone)]
+pub(crate) struct Fractal {
+    formula: Formula,
+    power: Exponent,
+    julia: Option<Complex<f64>>,
+    escape_radius_sqr: f64,
+    track_derivative: bool,
+    bulb_checks: bool,
+    cycle_detection: bool,
+    reference: Option<ReferenceOrbit>,
+}
+
+/// Escape radius large enough for the normalized iteration count to be free 
+/// of visible discontinuities
+pub(crate) const SMOOTH_ESCAPE_RADIUS: f64 = 256.0;
+
+/// Squared distance below which an orbit is considered to have returned to a 
+/// previous value, and therefore to be periodic
+const PERIODICITY_EPSILON_SQR: f64 = 1e-24;
+
+/// Return the period of the attracting cycle of `c` if it lies in the main 
+/// cardioid (period 1) or the period-2 bulb of the Mandelbrot set
+fn cardioid_or_bulb_period(c: Complex<f64>) -> Option<usize> {
+    let x = c.re - 0.25;
+    let y_sqr = c.im * c.im;
+    let q = x * x + y_sqr;
+    if q * (q + x) <= 0.25 * y_sqr {
+        Some(1)
+    } else if (c.re + 1.0) * (c.re + 1.0) + y_sqr <= 0.0625 {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+impl Fractal {
+    pub(crate) fn new(formula: Formula, power: Exponent, julia: Option<Complex<f64>>) -> Self {
+        let escape_radius = Self::escape_radius(formula.degree(power), julia);
+        Fractal {
+            formula,
+            power,
+            julia,
+            escape_radius_sqr: escape_radius * escape_radius,
+            track_derivative: false,
+            bulb_checks: true,
+            cycle_detection: true,
+            reference: None,
+        }
+    }
+
+    /// Draw the Mandelbrot set around a center given in arbitrary precision. 
+    /// Points passed to [`Fractal::iterate_point`] are then offsets from the 
+    /// center, and interior checks are skipped.
+    pub(crate) fn with_reference_orbit(
+        mut self,
+        center: &BigComplex,
+        bits: u64,
+        num_iter: usize,
+    ) -> Self {
+        debug_assert!(self.formula == Formula::Mandelbrot && self.julia.is_none());
+        let escape_radius = self.escape_radius_sqr.sqrt().ceil() as i64;
+        self.reference = Some(ReferenceOrbit::new(center, bits, num_iter, escape_radius));
+        self
+    }
+
+    /// Iterate points in the main cardioid and period-2 bulb of the 
+    /// Mandelbrot set like any other instead of recognizing them without 
+    /// iterating
+    pub(crate) fn without_bulb_checks(mut self) -> Self {
+        self.bulb_checks = false;
+        self
+    }
+
+    /// Follow orbits that settle into a cycle for the full number of 
+    /// iterations instead of stopping once the cycle is detected
+    pub(crate) fn without_cycle_detection(mut self) -> Self {
+        self.cycle_detection = false;
+        self
+    }
+
+    /// Iterate every point for the full number of iterations instead of 
+    /// detecting interior points early
+    pub(crate) fn without_interior_checks(self) -> Self {
+        self.without_bulb_checks().without_cycle_detection()
+    }
+
+    /// Track the derivative of each orbit, which is required for distance 
+    /// estimation. The formula must be holomorphic.
+    pub(crate) fn with_derivative(mut self) -> Self {
+        debug_assert!(self.formula.is_holomorphic());
+        self.track_derivative = true;
+        self
+    }
+
+    /// Raise the escape radius to at least `radius`
+    pub(crate) fn with_escape_radius(mut self, radius: f64) -> Self {
+        self.escape_radius_sqr = self.escape_radius_sqr.max(radius * radius);
+        self
+    }
+
+    pub(crate) fn formula(&self) -> Formula {
+        self.formula
+    }
+
+    pub(crate) fn power(&self) -> Exponent {
+        self.power
+    }
+
+    pub(crate) fn julia(&self) -> Option<Complex<f64>> {
+        self.julia
+    }
+
+    /// Return the escape radius (the "bailout") in use
+    pub(crate) fn bailout(&self) -> f64 {
+        self.escape_radius_sqr.sqrt()
+    }
+
+    pub(crate) fn tracks_derivative(&self) -> bool {
+        self.track_derivative
+    }
+
+    /// Return whether interior points are recognized early, which is never 
+    /// the case in a deep zoom
+    pub(crate) fn checks_interior(&self) -> bool {
+        self.checks_bulbs() || self.detects_cycles()
+    }
+
+    /// Return whether points in the main cardioid and period-2 bulb are 
+    /// recognized without iterating, which only applies to the Mandelbrot 
+    /// set itself
+    pub(crate) fn checks_bulbs(&self) -> bool {
+        self.bulb_checks
+            && self.formula == Formula::Mandelbrot
+            && self.julia.is_none()
+            && self.reference.is_none()
+    }
+
+    /// Return whether orbits that settle into a cycle are stopped early, and 
+    /// hence whether the periods of interior points are found
+    pub(crate) fn detects_cycles(&self) -> bool {
+        self.cycle_detection && self.reference.is_none()
+    }
+
+    /// Return a radius that bounded orbits never leave
+    pub(crate) fn interior_radius(&self) -> f64 {
+        Self::escape_radius(self.degree(), self.julia)
+    }
+
+    /// Return the degree of the formula being iterated
+    pub(crate) fn degree(&self) -> f64 {
+        self.formula.degree(self.power)
+    }
+
+    /// Return a radius beyond which every orbit of a formula of degree `d` 
+    /// is guaranteed to escape, namely max(|c|, 2^(1/(d - 1))). In the 
+    /// parameter plane, any `c` outside this radius escapes after one step.
+    fn escape_radius(d: f64, julia: Option<Complex<f64>>) -> f64 {
+        let radius = 2.0_f64.powf((d - 1.0).recip());
+        match julia {
+            Some(c) => radius.max(c.norm()),
+            None => radius,
+        }
+    }
+
+    /// Iterate the formula for a point in the plane of the fractal to 
+    /// determine whether its orbit is bounded. Return the moduli of z and its 
+    /// derivative at escape for an orbit that escapes, and the modulus of z 
+    /// after the last iteration, the smallest modulus of z along the way and 
+    /// the period of the orbit, if found, for one that doesn't.
+    ///
+    /// Unless disabled, points in the main cardioid and period-2 bulb of the 
+    /// Mandelbrot set are recognized without iterating, and orbits that 
+    /// become periodic are recognized using Brent's cycle detection, which 
+    /// cuts their iteration short.
+    pub(crate) fn iterate_point(&self, point: Complex<f64>, num_iter: usize) -> Orbit {
+        if let Some(ref reference) = self.reference {
+            return reference.iterate_delta(
+                point,
+                num_iter,
+                self.escape_radius_sqr,
+                self.track_derivative,
+            );
+        }
+        let (mut z, c, mut dz, dc) = match self.julia {
+            Some(c) => (point, c, Complex::new(1.0, 0.0), Complex::new(0.0, 0.0)),
+            None => (Complex::new(0.0, 0.0), point, Complex::new(0.0, 0.0), Complex::new(1.0, 0.0)),
+        };
+        if self.checks_bulbs() {
+            if let Some(period) = cardioid_or_bulb_period(c) {
+                return Orbit::Bounded(Interior::of_period(period));
+            }
+        }
+        let mut min_norm_sqr = f64::INFINITY;
+        let mut saved = z;
+        let mut cycle_length = 1;
+        let mut steps_since_saved = 0;
+        for i in 0..num_iter {
+            let norm_sqr = z.norm_sqr();
+            if norm_sqr > self.escape_radius_sqr {
+                return Orbit::Escaped(Escape::new(i, z, dz));
+            }
+            if i > 0 {
+                min_norm_sqr = min_norm_sqr.min(norm_sqr);
+            }
+            if self.track_derivative {
+                dz = self.formula.differentiate(z, self.power) * dz + dc;
+            }
+            z = self.formula.step(z, c, self.power);
+            if self.cycle_detection {
+                if (z - saved).norm_sqr() < PERIODICITY_EPSILON_SQR {
+                    return Orbit::Bounded(Interior::new(z, min_norm_sqr, steps_since_saved + 1));
+                }
+                steps_since_saved += 1;
+                if steps_since_saved == cycle_length {
+                    saved = z;
+                    steps_since_saved = 0;
+                    cycle_length *= 2;
+                }
+            }
+        }
+        Orbit::Bounded(Interior::new(z, min_norm_sqr, 0))
+    }
+
+    /// Return the exterior distance estimate |z| ln |z| / |dz| of an escaped 
+    /// orbit, an approximation of the distance from the point to the set. 
+    /// Requires the derivative to have been tracked.
+    pub(crate) fn distance_estimate(&self, escape: &Escape) -> f64 {
+        escape.abs_z * escape.abs_z.ln() / escape.abs_dz
+    }
+
+    /// Return the normalized iteration count i + 1 - log_d(ln |z|) of an 
+    /// escaped orbit, a continuous counterpart to the escape time
+    pub(crate) fn smooth_escape_time(&self, escape: &Escape) -> f64 {
+        let log_modulus = escape.abs_z.ln();
+        let nu = escape.iter as f64 + 1.0 - log_modulus.ln() / self.degree().ln();
+        nu.max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::Complex;
+
+    use crate::fractal::{
+        cardioid_or_bulb_period, Exponent, Formula, Fractal, Interior, Orbit, SMOOTH_ESCAPE_RADIUS,
+    };
+
+    #[test]
+    fn iterate_point_test() {
+        let num_iter = 1000;
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None);
+
+        let result1 = fractal.iterate_point(Complex::new(0.0, 0.0), num_iter);
+        assert_eq!(result1, Orbit::Bounded(Interior::of_period(1)));
+
+        let result2 = fractal.iterate_point(Complex::new(1.0, 0.0), num_iter);
+        assert_eq!(result2.escape().unwrap().iter, 3);
+        assert_eq!(result2.escape().unwrap().abs_z, 5.0);
+    }
+
+    #[test]
+    fn iterate_point_interior_test() {
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None);
+
+        // Cycle detection finds the period of an orbit outside the main 
+        // cardioid and period-2 bulb, here at the center of a period-3 bulb
+        let c = Complex::new(-0.122561166876654, 0.744861766619744);
+        let orbit = fractal.iterate_point(c, 1000);
+        assert!(matches!(orbit, Orbit::Bounded(interior) if interior.period == 3));
+
+        // Without interior checks, the orbit 0, -0.25, -0.1875, ... of 
+        // c = -0.25 is followed to its attracting fixed point (1 - √2) / 2
+        let unchecked = fractal.clone().without_interior_checks();
+        match unchecked.iterate_point(Complex::new(-0.25, 0.0), 1000) {
+            Orbit::Bounded(interior) => {
+                assert!((interior.abs_z - (2.0_f64.sqrt() - 1.0) / 2.0).abs() < 1e-12);
+                assert_eq!(interior.min_abs_z, 0.1875);
+                assert_eq!(interior.period, 0);
+            }
+            Orbit::Escaped(_) => panic!("c = -0.25 doesn't escape"),
+        }
+
+        // Each check can be turned off on its own: cycle detection still 
+        // finds the fixed point of c = -0.25 without the bulb checks, and 
+        // the period-3 orbit goes unrecognized without cycle detection
+        let unchecked = fractal.clone().without_bulb_checks();
+        match unchecked.iterate_point(Complex::new(-0.25, 0.0), 1000) {
+            Orbit::Bounded(interior) => assert_eq!(interior.period, 1),
+            Orbit::Escaped(_) => panic!("c = -0.25 doesn't escape"),
+        }
+        let orbit = fractal.without_cycle_detection().iterate_point(c, 1000);
+        assert!(matches!(orbit, Orbit::Bounded(interior) if interior.period == 0));
+    }
+
+    #[test]
+    fn iterate_point_julia_test() {
+        let num_iter = 1000;
+        let fractal = Fractal::new(
+            Formula::Mandelbrot,
+            Formula::MULTIBROT_POWER,
+            Some(Complex::new(-1.0, 0.0)), // the basilica
+        );
+
+        let result1 = fractal.iterate_point(Complex::new(0.0, 0.0), num_iter);
+        assert!(result1.escape().is_none());
+
+        let result2 = fractal.iterate_point(Complex::new(2.0, 0.0), num_iter);
+        assert_eq!(result2.escape().unwrap().iter, 1);
+    }
+
+    #[test]
+    fn step_test() {
+        let z = Complex::new(1.0, -2.0);
+        let c = Complex::new(0.5, 0.25);
+        let d = Formula::MULTIBROT_POWER;
+
+        assert!((Formula::Mandelbrot.step(z, c, d) - Complex::new(-2.5, -3.75)).norm() < 1e-12);
+        assert_eq!(Formula::Multibrot.step(z, c, d), Complex::new(-10.5, 2.25));
+        assert_eq!(Formula::BurningShip.step(z, c, d), Complex::new(-2.5, 4.25));
+        assert_eq!(Formula::Tricorn.step(z, c, d), Complex::new(-2.5, 4.25));
+        assert_eq!(Formula::Celtic.step(z, c, d), Complex::new(3.5, -3.75));
+    }
+
+    #[test]
+    fn exponent_from_str_test() {
+        assert_eq!("4".parse::<Exponent>(), Ok(Exponent::Integer(4)));
+        assert_eq!("2.5".parse::<Exponent>(), Ok(Exponent::Real(2.5)));
+        assert!("1".parse::<Exponent>().is_err());
+        assert!("-3".parse::<Exponent>().is_err());
+        assert!("inf".parse::<Exponent>().is_err());
+        assert!("two".parse::<Exponent>().is_err());
+    }
+
+    #[test]
+    fn exponent_raise_test() {
+        let z = Complex::new(0.6, -0.8);
+        let integer = Exponent::Integer(5).raise(z);
+        let real = Exponent::Real(5.0).raise(z);
+        assert!((integer - real).norm() < 1e-12);
+    }
+
+    #[test]
+    fn smooth_escape_time_test() {
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None)
+            .with_escape_radius(SMOOTH_ESCAPE_RADIUS);
+
+        // The smooth escape time varies continuously across the jumps in the 
+        // integer escape time along the real axis
+        let mut previous = fractal.iterate_point(Complex::new(0.5, 0.0), 1000).escape().unwrap();
+        let mut num_jumps = 0;
+        for k in 1..=1000 {
+            let escape = fractal
+                .iterate_point(Complex::new(0.5 + k as f64 * 1e-3, 0.0), 1000)
+                .escape()
+                .unwrap();
+            if escape.iter != previous.iter {
+                num_jumps += 1;
+            }
+            let jump = fractal.smooth_escape_time(&previous) - fractal.smooth_escape_time(&escape);
+            assert!(jump.abs() < 0.1);
+            previous = escape;
+        }
+        assert!(num_jumps > 0);
+    }
+
+    #[test]
+    fn distance_estimate_test() {
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None)
+            .with_escape_radius(SMOOTH_ESCAPE_RADIUS)
+            .with_derivative();
+
+        // The distance from c = 2.5 to the set, whose rightmost point is 
+        // c = 0.25, is 2.25; the estimate is accurate to within a factor of 4
+        let escape = fractal.iterate_point(Complex::new(2.5, 0.0), 1000).escape().unwrap();
+        let distance = fractal.distance_estimate(&escape);
+        assert!(distance > 2.25 / 4.0 && distance < 2.25 * 4.0);
+
+        // The estimate shrinks as we approach the boundary
+        let escape = fractal.iterate_point(Complex::new(0.26, 0.0), 1000).escape().unwrap();
+        assert!(fractal.distance_estimate(&escape) < 0.01 * 4.0);
+    }
+
+    #[test]
+    fn exponent_differentiate_test() {
+        let z = Complex::new(0.6, -0.8);
+        let integer = Exponent::Integer(3).differentiate(z);
+        let real = Exponent::Real(3.0).differentiate(z);
+        assert!((integer - z * z * 3.0).norm() < 1e-12);
+        assert!((real - z * z * 3.0).norm() < 1e-12);
+    }
+
+    #[test]
+    fn cardioid_or_bulb_period_test() {
+        assert_eq!(cardioid_or_bulb_period(Complex::new(0.0, 0.0)), Some(1));
+        assert_eq!(cardioid_or_bulb_period(Complex::new(0.24, 0.0)), Some(1));
+        assert_eq!(cardioid_or_bulb_period(Complex::new(-0.74, 0.0)), Some(1));
+        assert_eq!(cardioid_or_bulb_period(Complex::new(-1.0, 0.2)), Some(2));
+        assert_eq!(cardioid_or_bulb_period(Complex::new(-0.76, 0.0)), Some(2));
+        assert_eq!(cardioid_or_bulb_period(Complex::new(0.26, 0.0)), None);
+        assert_eq!(cardioid_or_bulb_period(Complex::new(-1.3, 0.0)), None);
+        assert_eq!(cardioid_or_bulb_period(Complex::new(-0.12, 0.75)), None); // period-3 bulb
+    }
+
+    #[test]
+    fn interior_checks_test() {
+        // Interior checks change how quickly points are classified but never 
+        // the classification itself, except for points such as -2 whose 
+        // orbits land exactly on a cycle, which rounding errors carry out of 
+        // the set unless the cycle is detected; the grid avoids them
+        for formula in [Formula::Mandelbrot, Formula::BurningShip] {
+            let fractal = Fractal::new(formula, Formula::MULTIBROT_POWER, None);
+            let unchecked = Fractal::new(formula, Formula::MULTIBROT_POWER, None)
+                .without_interior_checks();
+            for j in 0..40 {
+                for k in 0..40 {
+                    let c = Complex::new(-1.97 + k as f64 * 0.06, -1.2 + j as f64 * 0.06);
+                    assert_eq!(
+                        fractal.iterate_point(c, 500).escape(),
+                        unchecked.iterate_point(c, 500).escape(),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn escape_radius_test() {
+        assert_eq!(Fractal::escape_radius(2.0, None), 2.0);
+        assert_eq!(Fractal::escape_radius(3.0, None), 2.0_f64.sqrt());
+        assert_eq!(Fractal::escape_radius(2.0, Some(Complex::new(3.0, 4.0))), 5.0);
+    }
+}
@@ -1,155 +1,982 @@
+mod animation;
 mod color;
+mod fractal;
+mod perturbation;
+mod render;
+mod view;
 
 use std::ffi::OsString;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::BufWriter;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process;
 
-use anyhow::Result;
-use clap::{crate_name, Parser};
-use image::{codecs::png::PngEncoder, ColorType, ImageEncoder, RgbImage};
+use anyhow::{bail, Result};
+use clap::{crate_name, ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use image::codecs::openexr::OpenExrEncoder;
+use image::codecs::png::PngEncoder;
+use image::{EncodableLayout, ImageBuffer, ImageEncoder, PixelWithColorType, Rgb, RgbImage, Rgba};
 use num::Complex;
-use rayon::iter::{ParallelBridge, ParallelIterator};
 use time::OffsetDateTime;
 
-use crate::color::palettes::PolarLuvPalette;
+use crate::animation::encoders::{AnimationEncoder, ColorTable, Container};
+use crate::animation::{Easing, Keyframe};
+use crate::color::palettes::{PolarLuvPalette, Wrap};
+use crate::color::{PolarLuv, RGB};
+use crate::fractal::{Exponent, Formula, Fractal, SMOOTH_ESCAPE_RADIUS};
+use crate::perturbation::{precision_for, BigComplex};
+use crate::render::escape_file::{self, EscapeData};
+use crate::render::{
+    ColorMap, Coloring, EscapeBuffer, InteriorColoring, Mapping, Paintable, Scene, Transparency,
+};
+use crate::view::{ComplexBoundingBox, Projection, Sampling, Transform};
 
-/// Iterate a complex number `c` to determine whether it's in the Mandelbrot 
-/// set. If so, return `None`. Otherwise, return an option containing the 
-/// number of iterations that `c` took to escape (the "escape time").
-fn iterate_point(c: Complex<f64>, num_iter: usize) -> Option<usize> {
-    let mut z: Complex<f64> = Complex::new(0.0, 0.0);
-    for i in 0..num_iter {
-        if z.norm_sqr() > 4.0 {
-            return Some(i);
+fn write_image_to_disk<P>(image: &ImageBuffer<P, Vec<P::Subpixel>>, out_path: &Path) -> Result<()>
+where
+    P: PixelWithColorType,
+    [P::Subpixel]: EncodableLayout,
+{
+    let file = File::create(out_path)?;
+    let writer = BufWriter::new(file);
+    let bytes = image.as_bytes();
+    match ImageFormat::from_path(out_path) {
+        ImageFormat::Png => {
+            let encoder = PngEncoder::new(writer);
+            encoder.write_image(bytes, image.width(), image.height(), P::COLOR_TYPE)?;
+        }
+        ImageFormat::OpenExr => {
+            let encoder = OpenExrEncoder::new(writer);
+            encoder.write_image(bytes, image.width(), image.height(), P::COLOR_TYPE)?;
         }
-        z = z.powf(2.0) + c
     }
-    None
+    Ok(())
 }
 
-/// A bounding box in the complex plane defined by its upper left vertex, 
-/// width and height
-struct ComplexBoundingBox {
-    upper_left: Complex<f64>,
-    dims: (f64, f64),
+/// Format of a still image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ImageFormat {
+    Png,
+    /// Floating-point components in linear light, unclipped
+    OpenExr,
 }
 
-impl ComplexBoundingBox {
-    pub(crate) fn new(
-        upper_left: Complex<f64>,
-        complex_height: f64,
-        aspect_ratio: f64,
-    ) -> Self {
-        ComplexBoundingBox {
-            upper_left,
-            dims: (complex_height * aspect_ratio, complex_height),
+impl ImageFormat {
+    /// Infer the format from the extension of a path, which defaults to PNG
+    fn from_path(path: &Path) -> ImageFormat {
+        let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("exr") => ImageFormat::OpenExr,
+            _ => ImageFormat::Png,
         }
     }
-
-    pub(crate) fn map_pixel_to_point(
-        &self,
-        pixel: (u32, u32),
-        image_dims: (u32, u32),
-    ) -> Complex<f64> {
-        Complex::new(
-            self.upper_left.re + pixel.0 as f64 * self.dims.0 / image_dims.0 as f64,
-            self.upper_left.im - pixel.1 as f64 * self.dims.1 / image_dims.1 as f64,
-        )
-    }
-}
-
-fn draw_fractal(
-    image: &mut RgbImage,
-    bounding_box: &ComplexBoundingBox,
-    max_iter: usize,
-    palette: &PolarLuvPalette,
-    reverse: bool,
-) {
-    let image_dims = image.dimensions();
-    image
-        .enumerate_rows_mut()
-        .par_bridge()
-        .for_each(|(_, mut pixels)| {
-            for p in &mut pixels {
-                let point = bounding_box.map_pixel_to_point((p.0, p.1), image_dims);
-                let result = iterate_point(point, max_iter);
-                *p.2 = match result {
-                    Some(i) => palette
-                        .map_scalar_to_color(i as f64 / max_iter as f64, reverse)
-                        .as_image_Rgb(),
-                    None => image::Rgb([0; 3])
-                };
-            }
-        });
 }
 
-fn write_image_to_disk(image: &RgbImage, out_path: &Path) -> Result<()> {
-    let file = File::create(out_path)?;
-    let png_writer = BufWriter::new(file);
-    let encoder = PngEncoder::new(png_writer);
-    encoder.write_image(image, image.width(), image.height(), ColorType::Rgb8)?;
-    Ok(())
+/// Number of bits per channel of a still image
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Depth {
+    #[value(name = "8")]
+    Eight,
+    #[value(name = "16")]
+    Sixteen,
 }
 
 #[derive(Parser)]
 #[clap(author, version, about)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    view: Option<ViewArgs>,
+
+    #[command(flatten)]
+    render: Option<RenderArgs>,
+
+    #[command(flatten)]
+    color: Option<ColorArgs>,
+
+    #[arg(short, long)]
+    out_file: Option<OsString>,
+
+    #[arg(long, value_enum, default_value_t = Depth::Eight)]
+    depth: Depth,
+
+    #[arg(long)]
+    escape_file: Option<OsString>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a zoom as a sequence of PNG images or as an animated GIF or APNG
+    Animate(AnimateArgs),
+    /// Render a view once and animate it by cycling the palette
+    Cycle(CycleArgs),
+    /// Color an escape-data file saved with --escape-file
+    Colorize(ColorizeArgs),
+}
+
+/// Options that locate and size the view of a still image
+#[derive(Args)]
+#[command(group(ArgGroup::new("location").required(true).args(["upper_left", "center", "deep_center"])))]
+#[command(group(ArgGroup::new("size").required(true).args(["cheight", "cwidth", "radius", "zoom"])))]
+struct ViewArgs {
+    #[arg(long)]
+    upper_left: Option<Complex<f64>>,
+
+    #[arg(long)]
+    center: Option<Complex<f64>>,
+
+    #[arg(long)]
+    deep_center: Option<BigComplex>,
+
+    #[arg(long)]
+    cheight: Option<f64>,
+
+    #[arg(long)]
+    cwidth: Option<f64>,
+
+    #[arg(long)]
+    radius: Option<f64>,
+
+    #[arg(short, long)]
+    zoom: Option<f64>,
+}
+
+impl ViewArgs {
+    /// Resolve the height of the bounding box from whichever size option was 
+    /// given. A zoom of 1 corresponds to a radius of 2, which frames the 
+    /// whole Mandelbrot set.
+    fn complex_height(&self, aspect_ratio: f64) -> f64 {
+        match (self.cheight, self.cwidth, self.radius, self.zoom) {
+            (Some(cheight), ..) => cheight,
+            (_, Some(cwidth), ..) => cwidth / aspect_ratio,
+            (_, _, Some(radius), _) => 2.0 * radius,
+            (.., Some(zoom)) => 4.0 / zoom,
+            _ => unreachable!("clap requires one size option"),
+        }
+    }
+}
+
+/// Options shared by still images and animations
+#[derive(Args)]
+struct RenderArgs {
     #[arg(short = 'W', long)]
     width: u32,
 
     #[arg(short = 'H', long)]
     height: u32,
 
+    #[arg(short, long, value_enum, default_value_t = Formula::Mandelbrot)]
+    formula: Formula,
+
+    #[arg(short = 'd', long)]
+    power: Option<Exponent>,
+
+    #[arg(long)]
+    julia: Option<Complex<f64>>,
+
+    #[arg(short, long)]
+    aspect_ratio: Option<f64>,
+
+    #[arg(long, allow_hyphen_values = true)]
+    rotate: Option<f64>,
+
+    #[arg(long, allow_hyphen_values = true)]
+    skew: Option<f64>,
+
+    #[arg(long)]
+    flip_horizontal: bool,
+
+    #[arg(long)]
+    flip_vertical: bool,
+
+    #[arg(long, conflicts_with_all = ["rotate", "skew", "flip_horizontal", "flip_vertical"])]
+    matrix: Option<Transform>,
+
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=16))]
+    samples: u32,
+
+    #[arg(long)]
+    adaptive: bool,
+
     #[arg(long)]
-    upper_left: Complex<f64>,
+    no_bulb_checks: bool,
 
     #[arg(long)]
-    cheight: f64,
+    no_cycle_detection: bool,
 
+    #[arg(long)]
+    corner_sampling: bool,
+
+    #[arg(long, value_enum, default_value_t = Projection::Linear)]
+    projection: Projection,
+
+    #[arg(long, default_value_t = 16, requires = "adaptive")]
+    threshold: u8,
+
+    #[arg(short = 'N', long)]
+    max_iter: Option<usize>,
+
+    #[arg(long, conflicts_with = "max_iter")]
+    auto_iter: bool,
+}
+
+impl RenderArgs {
+    fn image_dims(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn aspect_ratio(&self) -> f64 {
+        self.aspect_ratio.unwrap_or(self.width as f64 / self.height as f64)
+    }
+
+    fn max_iter(&self) -> usize {
+        self.max_iter.unwrap_or(1000)
+    }
+
+    /// Return the maximum number of iterations for a view of the fractal 
+    /// built by `fractal_for`, choosing it from a preview of the view with 
+    /// `--auto-iter`
+    fn max_iter_for<F>(&self, bounding_box: &ComplexBoundingBox, fractal_for: F) -> usize
+    where
+        F: Fn(usize) -> Fractal,
+    {
+        if self.auto_iter {
+            render::choose_max_iter(bounding_box, self.image_dims(), fractal_for)
+        } else {
+            self.max_iter()
+        }
+    }
+
+    /// Compose the transformations of the bounding box in the order flip, 
+    /// skew, rotate
+    fn transform(&self) -> Transform {
+        if let Some(matrix) = self.matrix {
+            return matrix;
+        }
+        let mut transform = Transform::IDENTITY;
+        if self.flip_horizontal {
+            transform = transform.then(&Transform::FLIP_HORIZONTAL);
+        }
+        if self.flip_vertical {
+            transform = transform.then(&Transform::FLIP_VERTICAL);
+        }
+        if let Some(degrees) = self.skew {
+            transform = transform.then(&Transform::skew(degrees));
+        }
+        if let Some(degrees) = self.rotate {
+            transform = transform.then(&Transform::rotation(degrees));
+        }
+        transform
+    }
+
+    /// Apply the transformation, sampling and projection options to a 
+    /// bounding box
+    fn frame(&self, bounding_box: ComplexBoundingBox) -> ComplexBoundingBox {
+        bounding_box
+            .with_transform(self.transform())
+            .with_sampling(if self.corner_sampling { Sampling::Corner } else { Sampling::Center })
+            .with_projection(self.projection)
+    }
+
+    /// Validate the fractal and coloring options and build the fractal they 
+    /// describe
+    fn fractal(&self, color: &ColorArgs) -> Result<Fractal> {
+        let power = match (self.formula, self.power) {
+            (Formula::Multibrot, power) => power.unwrap_or(Formula::MULTIBROT_POWER),
+            (_, Some(_)) => bail!("--power applies only to the multibrot formula"),
+            (_, None) => Formula::MULTIBROT_POWER,
+        };
+        let coloring = color.coloring;
+        if coloring.requires_derivative() && !self.formula.is_holomorphic() {
+            bail!("distance estimation requires the mandelbrot or multibrot formula");
+        }
+        if self.adaptive && self.samples == 1 {
+            bail!("--adaptive requires more than one sample per pixel");
+        }
+        color.validate((!self.auto_iter).then(|| self.max_iter()))?;
+        let mut fractal = Fractal::new(self.formula, power, self.julia);
+        if coloring != Coloring::EscapeTime {
+            fractal = fractal.with_escape_radius(SMOOTH_ESCAPE_RADIUS);
+        }
+        if coloring.requires_derivative() {
+            fractal = fractal.with_derivative();
+        }
+        if color.interior == InteriorColoring::Period && self.no_cycle_detection {
+            bail!("--interior period requires cycle detection");
+        }
+        if self.no_bulb_checks {
+            fractal = fractal.without_bulb_checks();
+        }
+        if self.no_cycle_detection {
+            fractal = fractal.without_cycle_detection();
+        }
+        if color.interior.requires_full_orbit() {
+            fractal = fractal.without_interior_checks();
+        }
+        Ok(fractal)
+    }
+
+    /// Draw a scene, supersampling either every pixel or, in adaptive mode, 
+    /// only those that need it. Return the number of pixels refined.
+    fn draw<P>(
+        &self,
+        image: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+        scene: &Scene,
+        color_map: &ColorMap,
+    ) -> usize
+    where
+        P: Paintable,
+        P::Subpixel: Send,
+    {
+        if self.adaptive {
+            let (buffer, num_refined) = self.compute(scene, color_map);
+            buffer.paint(image, scene.fractal, scene.max_iter, color_map);
+            num_refined
+        } else {
+            EscapeBuffer::draw(image, scene, color_map, self.samples);
+            0
+        }
+    }
+
+    /// Iterate the orbits of a scene, supersampling either every pixel or, 
+    /// in adaptive mode, only those that differ strongly from their neighbors 
+    /// when painted with a color map. Return the outcome of every orbit and 
+    /// the number of pixels refined.
+    fn compute(&self, scene: &Scene, color_map: &ColorMap) -> (EscapeBuffer, usize) {
+        let image_dims = self.image_dims();
+        if self.adaptive {
+            let mut buffer = EscapeBuffer::compute(scene, image_dims, 1);
+            let mut painting = RgbImage::new(image_dims.0, image_dims.1);
+            buffer.paint(&mut painting, scene.fractal, scene.max_iter, color_map);
+            let num_refined = buffer.refine(&painting, scene, self.samples, self.threshold);
+            (buffer, num_refined)
+        } else {
+            (EscapeBuffer::compute(scene, image_dims, self.samples), 0)
+        }
+    }
+
+    fn report_refined(&self, num_refined: usize, num_pixels: usize) {
+        if self.adaptive {
+            eprintln!("{}: refined {} of {} pixels", crate_name!(), num_refined, num_pixels);
+        }
+    }
+
+    /// Report the fewest and most iterations chosen with `--auto-iter`
+    fn report_max_iter(&self, fewest: usize, most: usize) {
+        match (self.auto_iter, fewest == most) {
+            (true, true) => eprintln!("{}: chose {} iterations", crate_name!(), most),
+            (true, false) => eprintln!("{}: chose {} to {} iterations", crate_name!(), fewest, most),
+            (false, _) => {}
+        }
+    }
+}
+
+/// Options that choose the palette and how colors are picked from it
+#[derive(Args)]
+struct ColorArgs {
     #[arg(short, long)]
     palette: OsString,
 
     #[arg(short, long)]
     reverse: bool,
 
-    #[arg(short, long)]
-    aspect_ratio: Option<f64>,
+    #[arg(short, long, value_enum, default_value_t = Coloring::EscapeTime)]
+    coloring: Coloring,
 
-    #[arg(short = 'N', long)]
-    max_iter: Option<usize>,
+    #[arg(long, value_enum, default_value_t = Mapping::Linear)]
+    mapping: Mapping,
+
+    #[arg(long)]
+    mapping_exponent: Option<f64>,
+
+    #[arg(long)]
+    min_escape: Option<f64>,
+
+    #[arg(long)]
+    max_escape: Option<f64>,
+
+    #[arg(long, default_value_t = 1.0)]
+    thickness: f64,
+
+    #[arg(long, value_enum, default_value_t = Wrap::Clamp)]
+    wrap: Wrap,
+
+    #[arg(long, default_value_t = 1.0)]
+    palette_repeats: f64,
+
+    #[arg(long, value_enum, default_value_t = InteriorColoring::Solid)]
+    interior: InteriorColoring,
+
+    #[arg(long)]
+    interior_palette: Option<OsString>,
+
+    #[arg(long)]
+    interior_color: Option<PolarLuv>,
+
+    #[arg(long, conflicts_with_all = ["interior", "interior_palette", "interior_color"])]
+    transparent_interior: bool,
+
+    #[arg(long, value_parser = parse_escape_range)]
+    transparent_escape: Option<(f64, f64)>,
+}
+
+impl ColorArgs {
+    /// Validate the coloring options for a render of at most `max_iter` 
+    /// iterations per point, which is `None` if it's yet to be chosen
+    fn validate(&self, max_iter: Option<usize>) -> Result<()> {
+        let has_range = self.min_escape.is_some() || self.max_escape.is_some();
+        if (self.mapping != Mapping::Linear || has_range) && self.coloring.requires_derivative() {
+            bail!("--mapping, --min-escape and --max-escape apply only to escape-time and smooth coloring");
+        }
+        match (self.mapping, self.mapping_exponent) {
+            (Mapping::Power, Some(exponent)) if !(exponent > 0.0 && exponent.is_finite()) => {
+                bail!("--mapping-exponent must be positive")
+            }
+            (Mapping::Power, None) => bail!("--mapping power requires --mapping-exponent"),
+            (Mapping::Power, Some(_)) | (_, None) => {}
+            (_, Some(_)) => bail!("--mapping-exponent applies only to --mapping power"),
+        }
+        let min = self.min_escape.unwrap_or(0.0);
+        // Nothing escapes without iterating, so -N 0 leaves the range open
+        let max = self.max_escape.or_else(|| max_iter.filter(|&n| n > 0).map(|n| n as f64));
+        if !(min >= 0.0 && max.map_or(min.is_finite(), |max| max.is_finite() && min < max)) {
+            bail!("--min-escape must be at least 0 and less than --max-escape, which defaults to --max-iter");
+        }
+        if !(self.thickness > 0.0 && self.thickness.is_finite()) {
+            bail!("--thickness must be positive");
+        }
+        if !(self.palette_repeats > 0.0 && self.palette_repeats.is_finite()) {
+            bail!("--palette-repeats must be positive");
+        }
+        if self.interior == InteriorColoring::Solid && self.interior_palette.is_some() {
+            bail!("--interior-palette requires --interior final-modulus, period or min-modulus");
+        }
+        if self.interior.requires_full_orbit() && self.interior_color.is_some() {
+            bail!("--interior-color applies only to --interior solid and period");
+        }
+        Ok(())
+    }
+
+    fn transparency(&self) -> Transparency {
+        Transparency {
+            interior: self.transparent_interior,
+            escape_range: self.transparent_escape,
+        }
+    }
+
+    /// Load the palette and, if one is given, the interior palette
+    fn palettes(&self) -> Result<(PolarLuvPalette, Option<PolarLuvPalette>)> {
+        let palette = PolarLuvPalette::new(Path::new(&self.palette))?;
+        let interior_palette = match self.interior_palette {
+            Some(ref path) => Some(PolarLuvPalette::new(Path::new(path))?),
+            None => None,
+        };
+        Ok((palette, interior_palette))
+    }
+
+    /// Build a color map from the palettes, where the interior palette 
+    /// defaults to the palette
+    fn color_map<'a>(
+        &self,
+        palette: &'a PolarLuvPalette,
+        interior_palette: Option<&'a PolarLuvPalette>,
+    ) -> ColorMap<'a> {
+        ColorMap {
+            palette,
+            reverse: self.reverse,
+            coloring: self.coloring,
+            mapping: self.mapping,
+            exponent: self.mapping_exponent.unwrap_or(1.0),
+            escape_range: (self.min_escape.unwrap_or(0.0), self.max_escape),
+            thickness: self.thickness,
+            wrap: self.wrap,
+            repeats: self.palette_repeats,
+            offset: 0.0,
+            interior: self.interior,
+            interior_palette: interior_palette.unwrap_or(palette),
+            interior_color: self.interior_color.as_ref().map_or(RGB::BLACK, PolarLuv::as_RGB),
+            transparency: self.transparency(),
+        }
+    }
+}
+
+/// Parse a range of escape times given as `'min,max'`
+fn parse_escape_range(s: &str) -> Result<(f64, f64), String> {
+    let invalid = || format!("expected two comma-separated escape times, got `{}`", s);
+    let ends = s
+        .split(',')
+        .map(|end| end.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    match ends[..] {
+        [min, max] if min >= 0.0 && min <= max && max.is_finite() => Ok((min, max)),
+        [_, _] => Err(format!("escape times `{}` must be at least 0 and in increasing order", s)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Options that describe the path of an animation
+#[derive(Args)]
+#[command(group(ArgGroup::new("path").required(true).args(["from_center", "keyframes"])))]
+struct AnimateArgs {
+    #[command(flatten)]
+    render: RenderArgs,
+
+    #[command(flatten)]
+    color: ColorArgs,
+
+    #[arg(long, requires_all = ["from_zoom", "to_center", "to_zoom"])]
+    from_center: Option<Complex<f64>>,
+
+    #[arg(long, requires = "from_center")]
+    from_zoom: Option<f64>,
+
+    #[arg(long, requires = "from_center")]
+    to_center: Option<Complex<f64>>,
+
+    #[arg(long, requires = "from_center")]
+    to_zoom: Option<f64>,
+
+    #[arg(long)]
+    keyframes: Option<OsString>,
+
+    #[arg(long, value_enum, default_value_t = Easing::EaseInOut)]
+    easing: Easing,
+
+    #[command(flatten)]
+    sequence: SequenceArgs,
+}
+
+impl AnimateArgs {
+    /// Resolve the keyframes from either the keyframes file or the start and 
+    /// end options
+    fn keyframes(&self) -> Result<Vec<Keyframe>> {
+        let keyframes = match self.keyframes {
+            Some(ref path) => Keyframe::load(Path::new(path))?,
+            None => match (self.from_center, self.from_zoom, self.to_center, self.to_zoom) {
+                (Some(from_center), Some(from_zoom), Some(to_center), Some(to_zoom)) => vec![
+                    Keyframe { center: from_center, zoom: from_zoom },
+                    Keyframe { center: to_center, zoom: to_zoom },
+                ],
+                _ => unreachable!("clap requires either keyframes or a start and an end"),
+            },
+        };
+        if keyframes.len() < 2 {
+            bail!("an animation requires at least two keyframes");
+        }
+        if keyframes.iter().any(|k| !(k.zoom > 0.0 && k.zoom.is_finite())) {
+            bail!("every zoom must be positive and finite");
+        }
+        Ok(keyframes)
+    }
+}
+
+/// Options that describe a palette cycling animation
+#[derive(Args)]
+struct CycleArgs {
+    #[command(flatten)]
+    view: ViewArgs,
+
+    #[command(flatten)]
+    render: RenderArgs,
+
+    #[command(flatten)]
+    color: ColorArgs,
+
+    #[command(flatten)]
+    sequence: SequenceArgs,
+}
+
+/// Options that describe how to color an escape-data file
+#[derive(Args)]
+struct ColorizeArgs {
+    escape_file: OsString,
+
+    #[command(flatten)]
+    color: ColorArgs,
 
     #[arg(short, long)]
     out_file: Option<OsString>,
+
+    #[arg(long, value_enum, default_value_t = Depth::Eight)]
+    depth: Depth,
 }
 
-fn run(cli: &Cli) -> Result<()> {
-    let (image_width, image_height) = (cli.width, cli.height);
-    let upper_left = cli.upper_left;
-    let complex_height = cli.cheight;
-    let max_iter = cli.max_iter.unwrap_or(1000);
-    let palette_path = Path::new(&cli.palette);
-
-    let now_str;
-    let out_path = match cli.out_file {
-        Some(ref path) => Path::new(path),
-        None => {
-            now_str = format!("./{}.png", OffsetDateTime::now_utc().unix_timestamp());
-            Path::new(&now_str)
+/// Options that describe how the frames of an animation are written
+#[derive(Args)]
+struct SequenceArgs {
+    #[arg(long, value_parser = clap::value_parser!(u32).range(2..))]
+    frames: u32,
+
+    #[arg(long, default_value_t = 25, value_parser = clap::value_parser!(u16).range(1..=100))]
+    fps: u16,
+
+    #[arg(short, long, default_value = ".")]
+    out_dir: OsString,
+
+    #[arg(long, conflicts_with = "out_dir")]
+    out_file: Option<OsString>,
+}
+
+/// Writer of the frames of an animation to either numbered PNG images or a 
+/// single animated file
+struct FrameWriter<'a> {
+    out_dir: &'a Path,
+    /// Width of the zero-padded frame numbers in the names of images
+    digits: usize,
+    encoder: Option<AnimationEncoder>,
+}
+
+impl FrameWriter<'_> {
+    fn new<'a>(
+        sequence: &'a SequenceArgs,
+        image_dims: (u32, u32),
+        color_map: &ColorMap,
+    ) -> Result<FrameWriter<'a>> {
+        let out_dir = Path::new(&sequence.out_dir);
+        let encoder = match sequence.out_file {
+            Some(ref path) => {
+                let out_path = Path::new(path);
+                let container = match Container::from_path(out_path) {
+                    Some(container) => container,
+                    None => bail!("--out-file must end in .gif, .png or .apng"),
+                };
+                let color_table = ColorTable::new(color_map);
+                Some(AnimationEncoder::new(
+                    container,
+                    out_path,
+                    image_dims,
+                    sequence.frames,
+                    sequence.fps,
+                    color_table,
+                )?)
+            }
+            None => {
+                fs::create_dir_all(out_dir)?;
+                None
+            }
+        };
+        let digits = (sequence.frames - 1).to_string().len().max(4);
+        Ok(FrameWriter { out_dir, digits, encoder })
+    }
+
+    fn write(&mut self, frame: u32, image: &RgbImage) -> Result<()> {
+        match self.encoder {
+            Some(ref mut encoder) => encoder.write_frame(image),
+            None => {
+                let name = format!("frame_{:0digits$}.png", frame, digits = self.digits);
+                write_image_to_disk(image, &self.out_dir.join(name))
+            }
         }
+    }
+
+    fn finish(self) -> Result<()> {
+        match self.encoder {
+            Some(encoder) => encoder.finish(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Build the bounding box and fractal of a still view, which for a deep zoom 
+/// carries the reference orbit, and resolve its maximum number of iterations
+fn resolve_view(
+    view: &ViewArgs,
+    render: &RenderArgs,
+    color: &ColorArgs,
+) -> Result<(ComplexBoundingBox, Fractal, usize)> {
+    let image_dims = render.image_dims();
+    let aspect_ratio = render.aspect_ratio();
+    let complex_height = view.complex_height(aspect_ratio);
+    if !(complex_height > 0.0 && complex_height.is_finite()) {
+        bail!("the bounding box must have a positive, finite size");
+    }
+    let bounding_box = render.frame(match (view.upper_left, view.center) {
+        (Some(upper_left), _) => ComplexBoundingBox::new(upper_left, complex_height, aspect_ratio),
+        (_, Some(center)) => ComplexBoundingBox::from_center(center, complex_height, aspect_ratio),
+        // Points in a deep zoom are offsets from the center
+        _ => ComplexBoundingBox::from_center(Complex::new(0.0, 0.0), complex_height, aspect_ratio),
+    });
+    if view.deep_center.is_some() && (render.formula != Formula::Mandelbrot || render.julia.is_some()) {
+        bail!("deep zooms support only the mandelbrot formula in the parameter plane");
+    }
+    if view.deep_center.is_some() && color.interior == InteriorColoring::Period {
+        bail!("--interior period requires cycle detection, which deep zooms skip");
+    }
+    let fractal = render.fractal(color)?;
+    // The bottom of the image has the smallest pixels in any projection
+    let bottom = (0.0, image_dims.1 as f64);
+    let bits = precision_for(bounding_box.pixel_size(bottom, image_dims));
+    let fractal_for = |max_iter| match view.deep_center {
+        Some(ref center) => fractal.clone().with_reference_orbit(center, bits, max_iter),
+        None => fractal.clone(),
+    };
+    let max_iter = render.max_iter_for(&bounding_box, fractal_for);
+    color.validate(Some(max_iter))?;
+    Ok((bounding_box, fractal_for(max_iter), max_iter))
+}
+
+/// Resolve the path of an image, which defaults to the current time in the 
+/// current directory
+fn out_path(out_file: &Option<OsString>) -> PathBuf {
+    match out_file {
+        Some(ref path) => PathBuf::from(path),
+        None => PathBuf::from(format!("./{}.png", OffsetDateTime::now_utc().unix_timestamp())),
+    }
+}
+
+fn run_image(view: &ViewArgs, render: &RenderArgs, color: &ColorArgs, cli: &Cli) -> Result<()> {
+    let image_dims = render.image_dims();
+    let out_path = out_path(&cli.out_file);
+    let format = ImageFormat::from_path(&out_path);
+    check_depth(format, cli.depth)?;
+
+    let (bounding_box, fractal, max_iter) = resolve_view(view, render, color)?;
+    render.report_max_iter(max_iter, max_iter);
+    let (palette, interior_palette) = color.palettes()?;
+    let color_map = color.color_map(&palette, interior_palette.as_ref());
+    let scene = Scene {
+        bounding_box: &bounding_box,
+        fractal: &fractal,
+        max_iter,
     };
-    let reverse = cli.reverse;
-    let aspect_ratio = cli.aspect_ratio.unwrap_or(image_width as f64 / image_height as f64);
+    let escape_file = cli
+        .escape_file
+        .as_ref()
+        .map(|path| (Path::new(path), view.deep_center.as_ref()));
+    let source = DrawnScene { render, scene: &scene, color_map: &color_map, escape_file };
+    let transparent = color_map.transparency.is_some();
+    let num_refined = save_image(&source, image_dims, format, transparent, cli.depth, &out_path)?;
+    render.report_refined(num_refined, image_dims.0 as usize * image_dims.1 as usize);
+    Ok(())
+}
+
+/// Ensure that a bit depth other than the default is only asked of a format 
+/// that offers it
+fn check_depth(format: ImageFormat, depth: Depth) -> Result<()> {
+    if format == ImageFormat::OpenExr && depth != Depth::Eight {
+        bail!("--depth applies only to PNG images; OpenEXR images hold 32-bit floats");
+    }
+    Ok(())
+}
+
+/// Something that fills images made up of any type of pixel, which 
+/// [`save_image`] chooses at run time
+trait ImageSource {
+    /// Fill `image` and return the number of pixels refined
+    fn fill<P>(&self, image: &mut ImageBuffer<P, Vec<P::Subpixel>>) -> Result<usize>
+    where
+        P: Paintable,
+        P::Subpixel: Send;
+}
+
+/// A scene to draw, whose escape data is saved along with the center of a 
+/// deep zoom first if requested
+struct DrawnScene<'a> {
+    render: &'a RenderArgs,
+    scene: &'a Scene<'a>,
+    color_map: &'a ColorMap<'a>,
+    escape_file: Option<(&'a Path, Option<&'a BigComplex>)>,
+}
+
+impl ImageSource for DrawnScene<'_> {
+    fn fill<P>(&self, image: &mut ImageBuffer<P, Vec<P::Subpixel>>) -> Result<usize>
+    where
+        P: Paintable,
+        P::Subpixel: Send,
+    {
+        let (render, scene, color_map) = (self.render, self.scene, self.color_map);
+        match self.escape_file {
+            Some((escape_path, deep_center)) => {
+                let (buffer, num_refined) = render.compute(scene, color_map);
+                escape_file::save(escape_path, scene, deep_center, &buffer)?;
+                buffer.paint(image, scene.fractal, scene.max_iter, color_map);
+                Ok(num_refined)
+            }
+            None => Ok(render.draw(image, scene, color_map)),
+        }
+    }
+}
+
+/// Escape data loaded from a file, to paint with a color map
+struct PaintedEscapeData<'a> {
+    data: &'a EscapeData,
+    color_map: &'a ColorMap<'a>,
+}
 
-    let mut image = RgbImage::new(image_width, image_height);
-    let bounding_box = ComplexBoundingBox::new(upper_left, complex_height, aspect_ratio);
-    let palette = PolarLuvPalette::new(palette_path)?;
-    draw_fractal(&mut image, &bounding_box, max_iter, &palette, reverse);
+impl ImageSource for PaintedEscapeData<'_> {
+    fn fill<P>(&self, image: &mut ImageBuffer<P, Vec<P::Subpixel>>) -> Result<usize>
+    where
+        P: Paintable,
+        P::Subpixel: Send,
+    {
+        let data = self.data;
+        data.buffer.paint(image, &data.fractal, data.max_iter, self.color_map);
+        Ok(0)
+    }
+}
+
+/// Fill an image from `source` with the type of pixel that suits `format`, 
+/// transparency and `depth`, and write it to disk. Return the number of 
+/// pixels refined.
+fn save_image<S: ImageSource>(
+    source: &S,
+    image_dims: (u32, u32),
+    format: ImageFormat,
+    transparent: bool,
+    depth: Depth,
+    out_path: &Path,
+) -> Result<usize> {
+    match (format, transparent, depth) {
+        (ImageFormat::Png, false, Depth::Eight) => save::<Rgb<u8>, _>(source, image_dims, out_path),
+        (ImageFormat::Png, true, Depth::Eight) => save::<Rgba<u8>, _>(source, image_dims, out_path),
+        (ImageFormat::Png, false, Depth::Sixteen) => {
+            save::<Rgb<u16>, _>(source, image_dims, out_path)
+        }
+        (ImageFormat::Png, true, Depth::Sixteen) => {
+            save::<Rgba<u16>, _>(source, image_dims, out_path)
+        }
+        (ImageFormat::OpenExr, false, _) => save::<Rgb<f32>, _>(source, image_dims, out_path),
+        (ImageFormat::OpenExr, true, _) => save::<Rgba<f32>, _>(source, image_dims, out_path),
+    }
+}
+
+/// Fill an image made up of pixels of type `P` from `source` and write it to 
+/// disk. Return the number of pixels refined.
+fn save<P, S>(source: &S, image_dims: (u32, u32), out_path: &Path) -> Result<usize>
+where
+    P: Paintable,
+    P::Subpixel: Send,
+    [P::Subpixel]: EncodableLayout,
+    S: ImageSource,
+{
+    let mut image = ImageBuffer::<P, Vec<P::Subpixel>>::new(image_dims.0, image_dims.1);
+    let num_refined = source.fill(&mut image)?;
     write_image_to_disk(&image, out_path)?;
+    Ok(num_refined)
+}
+
+fn run_animation(args: &AnimateArgs) -> Result<()> {
+    let render = &args.render;
+    let color = &args.color;
+    let sequence = &args.sequence;
+    let image_dims = render.image_dims();
+
+    if color.transparency().is_some() {
+        bail!("--transparent-interior and --transparent-escape apply only to still images");
+    }
+    let keyframes = args.keyframes()?;
+    let aspect_ratio = render.aspect_ratio();
+    let fractal = render.fractal(color)?;
+    let (palette, interior_palette) = color.palettes()?;
+    let color_map = color.color_map(&palette, interior_palette.as_ref());
+    let mut writer = FrameWriter::new(sequence, image_dims, &color_map)?;
+
+    let mut num_refined = 0;
+    let (mut fewest_iter, mut most_iter) = (usize::MAX, 0);
+    for frame in 0..sequence.frames {
+        let t = frame as f64 / (sequence.frames - 1) as f64;
+        let view = animation::interpolate(&keyframes, t, args.easing);
+        let bounding_box = render.frame(ComplexBoundingBox::from_center(
+            view.center,
+            4.0 / view.zoom,
+            aspect_ratio,
+        ));
+        let max_iter = render.max_iter_for(&bounding_box, |_| fractal.clone());
+        color.validate(Some(max_iter))?;
+        fewest_iter = fewest_iter.min(max_iter);
+        most_iter = most_iter.max(max_iter);
+        let scene = Scene {
+            bounding_box: &bounding_box,
+            fractal: &fractal,
+            max_iter,
+        };
+        let mut image = RgbImage::new(image_dims.0, image_dims.1);
+        num_refined += render.draw(&mut image, &scene, &color_map);
+        writer.write(frame, &image)?;
+    }
+    writer.finish()?;
+    let num_pixels = sequence.frames as usize * image_dims.0 as usize * image_dims.1 as usize;
+    render.report_refined(num_refined, num_pixels);
+    render.report_max_iter(fewest_iter, most_iter);
     Ok(())
 }
 
+fn run_cycle(args: &CycleArgs) -> Result<()> {
+    let render = &args.render;
+    let color = &args.color;
+    let sequence = &args.sequence;
+    let image_dims = render.image_dims();
+
+    if color.transparency().is_some() {
+        bail!("--transparent-interior and --transparent-escape apply only to still images");
+    }
+    let period = match color.wrap.period() {
+        Some(period) => period,
+        None => bail!("palette cycling requires --wrap repeat or --wrap mirror"),
+    };
+    let (bounding_box, fractal, max_iter) = resolve_view(&args.view, render, color)?;
+    render.report_max_iter(max_iter, max_iter);
+    let (palette, interior_palette) = color.palettes()?;
+    let color_map = color.color_map(&palette, interior_palette.as_ref());
+    let scene = Scene {
+        bounding_box: &bounding_box,
+        fractal: &fractal,
+        max_iter,
+    };
+    let (buffer, num_refined) = render.compute(&scene, &color_map);
+    render.report_refined(num_refined, image_dims.0 as usize * image_dims.1 as usize);
+
+    let mut writer = FrameWriter::new(sequence, image_dims, &color_map)?;
+    for frame in 0..sequence.frames {
+        // The palette stops one step short of a full cycle, so that the 
+        // animation loops seamlessly
+        let offset = period * frame as f64 / sequence.frames as f64;
+        let color_map = ColorMap { offset, ..color_map };
+        let mut image = RgbImage::new(image_dims.0, image_dims.1);
+        buffer.paint(&mut image, &fractal, scene.max_iter, &color_map);
+        writer.write(frame, &image)?;
+    }
+    writer.finish()
+}
+
+fn run_colorize(args: &ColorizeArgs) -> Result<()> {
+    let color = &args.color;
+    let out_path = out_path(&args.out_file);
+    let format = ImageFormat::from_path(&out_path);
+    check_depth(format, args.depth)?;
+
+    let data = EscapeData::load(Path::new(&args.escape_file))?;
+    color.validate(Some(data.max_iter))?;
+    if color.coloring.requires_derivative() && !data.fractal.tracks_derivative() {
+        bail!("the escape data holds no distance estimates; save it with --coloring distance or boundary");
+    }
+    if color.interior.requires_full_orbit() && data.fractal.checks_interior() {
+        bail!("the escape data holds interior orbits cut short by interior checks; save it with --interior final-modulus or min-modulus");
+    }
+    if color.interior == InteriorColoring::Period && !data.fractal.detects_cycles() {
+        bail!("the escape data holds no periods; save it with cycle detection, outside a deep zoom");
+    }
+    let (palette, interior_palette) = color.palettes()?;
+    let color_map = color.color_map(&palette, interior_palette.as_ref());
+    let source = PaintedEscapeData { data: &data, color_map: &color_map };
+    let transparent = color_map.transparency.is_some();
+    save_image(&source, data.buffer.dims, format, transparent, args.depth, &out_path)?;
+    Ok(())
+}
+
+fn run(cli: &Cli) -> Result<()> {
+    match (&cli.command, &cli.view, &cli.render, &cli.color) {
+        (Some(Command::Animate(args)), ..) => run_animation(args),
+        (Some(Command::Cycle(args)), ..) => run_cycle(args),
+        (Some(Command::Colorize(args)), ..) => run_colorize(args),
+        (None, Some(view), Some(render), Some(color)) => run_image(view, render, color, cli),
+        _ => unreachable!("clap requires either a subcommand or the image options"),
+    }
+}
+
 fn main() {
     let name = crate_name!();
     let cli = Cli::parse();
@@ -165,35 +992,173 @@ fn main() {
 #[cfg(test)]
 mod tests {
     pub(crate) mod float;
-    use crate::{iterate_point, Cli, ComplexBoundingBox};
-    use num::Complex;
+    use crate::render::Transparency;
+    use crate::{check_depth, Cli, Command, Depth, ImageFormat};
+
+    #[test]
+    fn complex_height_test() {
+        use clap::Parser;
+
+        let parse = |size: &[&str]| {
+            let args = ["fraczal", "-W=1", "-H=1", "-p=x", "--center=0"];
+            Cli::try_parse_from(args.iter().chain(size.iter())).map(|cli| cli.view.unwrap())
+        };
+        assert_eq!(parse(&["--cheight=2"]).unwrap().complex_height(2.0), 2.0);
+        assert_eq!(parse(&["--cwidth=3"]).unwrap().complex_height(2.0), 1.5);
+        assert_eq!(parse(&["--radius=0.5"]).unwrap().complex_height(2.0), 1.0);
+        assert_eq!(parse(&["--zoom=8"]).unwrap().complex_height(2.0), 0.5);
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--zoom=8", "--radius=1"]).is_err());
+        assert!(parse(&["--zoom=8", "--upper-left=0"]).is_err());
+    }
+
+    #[test]
+    fn animate_cli_test() {
+        use clap::Parser;
+
+        let parse = |path: &[&str]| {
+            let args = ["fraczal", "animate", "-W=1", "-H=1", "-p=x", "--frames=2"];
+            Cli::try_parse_from(args.iter().chain(path.iter()))
+        };
+        let cli = parse(&["--keyframes=k.json"]).unwrap();
+        assert!(cli.view.is_none() && cli.render.is_none());
+        assert!(matches!(cli.command, Some(Command::Animate(_))));
+        assert!(parse(&["--from-center=0", "--from-zoom=1", "--to-center=1", "--to-zoom=2"]).is_ok());
+        assert!(parse(&["--from-center=0", "--from-zoom=1"]).is_err());
+        assert!(parse(&["--keyframes=k.json", "--from-center=0"]).is_err());
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--keyframes=k.json", "--out-file=zoom.gif", "--fps=50"]).is_ok());
+        assert!(parse(&["--keyframes=k.json", "--out-file=zoom.gif", "--out-dir=frames"]).is_err());
+        assert!(parse(&["--keyframes=k.json", "--fps=0"]).is_err());
+        assert!(Cli::try_parse_from(["fraczal", "--center=0", "animate"]).is_err());
+    }
+
+    #[test]
+    fn cycle_cli_test() {
+        use clap::Parser;
+
+        let parse = |view: &[&str]| {
+            let args = ["fraczal", "cycle", "-W=1", "-H=1", "-p=x", "--frames=2", "--wrap=repeat"];
+            Cli::try_parse_from(args.iter().chain(view.iter()))
+        };
+        let cli = parse(&["--center=0", "-z=1"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Cycle(_))));
+        assert!(parse(&["--deep-center=0", "-z=1", "--out-file=cycle.gif"]).is_ok());
+        assert!(parse(&["--center=0"]).is_err());
+        assert!(parse(&[]).is_err());
+    }
+
+    #[test]
+    fn colorize_cli_test() {
+        use clap::Parser;
+
+        let parse = |args: &[&str]| Cli::try_parse_from(["fraczal"].iter().chain(args.iter()));
+        let cli = parse(&["colorize", "view.esc", "-p=x", "-c=smooth", "-o=view.png"]).unwrap();
+        assert!(cli.view.is_none() && cli.render.is_none() && cli.color.is_none());
+        assert!(matches!(cli.command, Some(Command::Colorize(_))));
+        assert!(parse(&["colorize", "view.esc"]).is_err());
+        assert!(parse(&["colorize", "-p=x"]).is_err());
+        assert!(parse(&["colorize", "view.esc", "-p=x", "--escape-file=view.esc"]).is_err());
+
+        let cli = parse(&["-W=1", "-H=1", "-p=x", "--center=0", "-z=1", "--escape-file=v.esc"]).unwrap();
+        assert!(cli.color.is_some() && cli.escape_file.is_some());
+    }
 
     #[test]
-    fn iterate_point_test() {
-        let num_iter = 1000;
+    fn depth_cli_test() {
+        use clap::Parser;
+
+        let parse = |args: &[&str]| Cli::try_parse_from(["fraczal"].iter().chain(args.iter()));
+        let cli = parse(&["-W=1", "-H=1", "-p=x", "--center=0", "-z=1"]).unwrap();
+        assert_eq!(cli.depth, Depth::Eight);
+        let cli = parse(&["-W=1", "-H=1", "-p=x", "--center=0", "-z=1", "--depth=16"]).unwrap();
+        assert_eq!(cli.depth, Depth::Sixteen);
+        match parse(&["colorize", "view.esc", "-p=x", "--depth=16"]).unwrap().command {
+            Some(Command::Colorize(args)) => assert_eq!(args.depth, Depth::Sixteen),
+            _ => panic!("expected the colorize subcommand"),
+        }
+        assert!(parse(&["-W=1", "-H=1", "-p=x", "--center=0", "-z=1", "--depth=12"]).is_err());
+    }
 
-        let result1 = iterate_point(Complex::new(0.0, 0.0), num_iter);
-        assert!(result1.is_none());
+    #[test]
+    fn image_format_test() {
+        use std::path::Path;
 
-        let result2 = iterate_point(Complex::new(1.0, 0.0), num_iter);
-        assert_eq!(result2.unwrap(), 3);
+        assert_eq!(ImageFormat::from_path(Path::new("view.png")), ImageFormat::Png);
+        assert_eq!(ImageFormat::from_path(Path::new("view.EXR")), ImageFormat::OpenExr);
+        assert_eq!(ImageFormat::from_path(Path::new("view")), ImageFormat::Png);
+        assert!(check_depth(ImageFormat::Png, Depth::Sixteen).is_ok());
+        assert!(check_depth(ImageFormat::OpenExr, Depth::Eight).is_ok());
+        assert!(check_depth(ImageFormat::OpenExr, Depth::Sixteen).is_err());
     }
 
     #[test]
-    fn map_pixel_to_point_test() {
-        let bounding_box = ComplexBoundingBox {
-            upper_left: Complex::<f64> { re: -1.0, im: 1.0 },
-            dims: (2.0, 2.0),
+    fn mapping_validate_test() {
+        use clap::Parser;
+
+        let validate = |mapping: &[&str]| {
+            let args = ["fraczal", "-W=1", "-H=1", "-p=x", "--center=0", "-z=1"];
+            let cli = Cli::try_parse_from(args.iter().chain(mapping.iter())).unwrap();
+            cli.color.unwrap().validate(Some(1000))
+        };
+        assert!(validate(&[]).is_ok());
+        assert!(validate(&["--mapping=log", "--min-escape=10", "--max-escape=200"]).is_ok());
+        assert!(validate(&["--mapping=power", "--mapping-exponent=0.5"]).is_ok());
+        assert!(validate(&["--mapping=power"]).is_err());
+        assert!(validate(&["--mapping=power", "--mapping-exponent=0"]).is_err());
+        assert!(validate(&["--mapping=sqrt", "--mapping-exponent=2"]).is_err());
+        assert!(validate(&["--min-escape=1000"]).is_err());
+        assert!(validate(&["--min-escape=10", "--max-escape=5"]).is_err());
+        assert!(validate(&["--max-escape=5000", "-c=smooth"]).is_ok());
+        assert!(validate(&["--max-escape=500", "-c=distance"]).is_err());
+        assert!(validate(&["--mapping=histogram", "-c=boundary"]).is_err());
+
+        // No point escapes with -N 0, which draws only the interior
+        let args = ["fraczal", "-W=1", "-H=1", "-p=x", "--center=0", "-z=1", "-N=0"];
+        assert!(Cli::try_parse_from(args).unwrap().color.unwrap().validate(Some(0)).is_ok());
+    }
+
+    #[test]
+    fn interior_validate_test() {
+        use clap::Parser;
+
+        let fractal = |interior: &[&str]| {
+            let args = ["fraczal", "-W=1", "-H=1", "-p=x", "--center=0", "-z=1"];
+            let cli = Cli::try_parse_from(args.iter().chain(interior.iter())).unwrap();
+            cli.render.unwrap().fractal(&cli.color.unwrap())
+        };
+        assert!(fractal(&[]).unwrap().checks_interior());
+        assert!(fractal(&["--interior-color=60,20,95"]).is_ok());
+        assert!(fractal(&["--interior=period", "--interior-palette=y"]).unwrap().checks_interior());
+        assert!(fractal(&["--interior=period", "--no-cycle-detection"]).is_err());
+        let fractal_without = |flag| fractal(&[flag]).unwrap();
+        assert!(!fractal_without("--no-bulb-checks").checks_bulbs());
+        assert!(fractal_without("--no-bulb-checks").detects_cycles());
+        assert!(fractal_without("--no-cycle-detection").checks_bulbs());
+        assert!(!fractal_without("--no-cycle-detection").detects_cycles());
+        assert!(!fractal(&["--interior=min-modulus"]).unwrap().checks_interior());
+        assert!(fractal(&["--interior=final-modulus", "--interior-color=60,20,95"]).is_err());
+        assert!(fractal(&["--interior-palette=y"]).is_err());
+        let args = ["fraczal", "-W=1", "-H=1", "-p=x", "--interior-color=60,20"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn transparency_validate_test() {
+        use clap::Parser;
+
+        let parse = |transparency: &[&str]| {
+            let args = ["fraczal", "-W=1", "-H=1", "-p=x", "--center=0", "-z=1"];
+            Cli::try_parse_from(args.iter().chain(transparency.iter()))
         };
-        let image_dims = (100, 100);
-        assert_eq!(
-            bounding_box.map_pixel_to_point((0, 0), image_dims),
-            Complex::new(-1.0, 1.0),
-        );
-        assert_eq!(
-            bounding_box.map_pixel_to_point(image_dims, image_dims),
-            Complex::new(1.0, -1.0),
-        );
+        let cli = parse(&["--transparent-interior", "--transparent-escape=0,20"]).unwrap();
+        let transparency = Transparency { interior: true, escape_range: Some((0.0, 20.0)) };
+        assert_eq!(cli.color.unwrap().transparency(), transparency);
+        assert!(!parse(&[]).unwrap().color.unwrap().transparency().is_some());
+        assert!(parse(&["--transparent-escape=20,10"]).is_err());
+        assert!(parse(&["--transparent-escape=-1,10"]).is_err());
+        assert!(parse(&["--transparent-escape=20"]).is_err());
+        assert!(parse(&["--transparent-interior", "--interior-color=60,20,95"]).is_err());
     }
 
     #[test]
@@ -0,0 +1,368 @@
+use std::fmt;
+use std::str::FromStr;
+
+use num::{BigInt, Complex, Integer, One, Signed, ToPrimitive, Zero};
+
+use crate::fractal::{Escape, Interior, Orbit};
+
+/// A signed fixed-point number with `bits` fractional bits
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BigFixed {
+    mantissa: BigInt,
+    bits: u64,
+}
+
+impl BigFixed {
+    fn zero(bits: u64) -> Self {
+        BigFixed { mantissa: BigInt::zero(), bits }
+    }
+
+    /// Parse a decimal number, optionally in scientific notation, rounding it 
+    /// to `bits` fractional bits. Powers of ten beyond ±`bits`, which are 
+    /// far too large or too small to matter at that precision, are rejected 
+    /// rather than computed.
+    pub(crate) fn parse(s: &str, bits: u64) -> Result<Self, String> {
+        let invalid = || format!("invalid decimal number `{}`", s);
+        let s = s.trim();
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (significand, exponent) = match unsigned.find(['e', 'E']) {
+            Some(index) => {
+                let exponent: i64 = unsigned[index + 1..].parse().map_err(|_| invalid())?;
+                (&unsigned[..index], exponent)
+            }
+            None => (unsigned, 0),
+        };
+        let (integer_digits, fraction_digits) = significand
+            .split_once('.')
+            .unwrap_or((significand, ""));
+        let digits = format!("{}{}", integer_digits, fraction_digits);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        // value = digits × 10^scale
+        let scale = exponent
+            .checked_sub(fraction_digits.len() as i64)
+            .filter(|scale| scale.unsigned_abs() <= bits)
+            .ok_or_else(|| format!("exponent of `{}` out of range", s))?;
+        let mut numerator: BigInt = digits.parse().map_err(|_| invalid())?;
+        numerator <<= bits as usize;
+        let mantissa = if scale >= 0 {
+            numerator * BigInt::from(10).pow(scale as u32)
+        } else {
+            let denominator = BigInt::from(10).pow((-scale) as u32);
+            (numerator + (&denominator >> 1usize)) / denominator
+        };
+        let mantissa = if negative { -mantissa } else { mantissa };
+        Ok(BigFixed { mantissa, bits })
+    }
+
+    pub(crate) fn to_f64(&self) -> f64 {
+        // Keep only the leading 64 bits so that the conversion can't overflow
+        let shift = self.mantissa.bits().saturating_sub(64);
+        let truncated = (&self.mantissa >> shift as usize).to_f64().unwrap_or(f64::NAN);
+        let exponent = shift as i64 - self.bits as i64;
+
+        // Scale in two steps so that the power of 2 itself can't underflow
+        let half = (exponent / 2) as i32;
+        truncated * 2.0_f64.powi(half) * 2.0_f64.powi(exponent as i32 - half)
+    }
+
+    fn add(&self, other: &BigFixed) -> BigFixed {
+        BigFixed { mantissa: &self.mantissa + &other.mantissa, bits: self.bits }
+    }
+
+    fn sub(&self, other: &BigFixed) -> BigFixed {
+        BigFixed { mantissa: &self.mantissa - &other.mantissa, bits: self.bits }
+    }
+
+    fn mul(&self, other: &BigFixed) -> BigFixed {
+        BigFixed {
+            mantissa: (&self.mantissa * &other.mantissa) >> self.bits as usize,
+            bits: self.bits,
+        }
+    }
+
+    fn is_greater_than_int(&self, n: i64) -> bool {
+        self.mantissa.abs() > BigInt::from(n) << self.bits as usize
+    }
+}
+
+impl fmt::Display for BigFixed {
+    /// Format the number in decimal with enough digits to parse back to the 
+    /// same value at the same precision
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // log10(2) < 0.302, so each fractional bit needs less than one 
+        // decimal digit
+        let digits = (self.bits * 302 + 999) / 1000 + 1;
+        let scale = BigInt::from(10).pow(digits as u32);
+        let half = (BigInt::one() << self.bits as usize) >> 1usize;
+        let scaled = (self.mantissa.abs() * &scale + half) >> self.bits as usize;
+        let (integer, fraction) = scaled.div_rem(&scale);
+        let sign = if self.mantissa.is_negative() { "-" } else { "" };
+        write!(f, "{}{}.{:0>width$}", sign, integer, fraction, width = digits as usize)
+    }
+}
+
+/// A complex number with fixed-point real and imaginary parts
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BigComplex {
+    re: BigFixed,
+    im: BigFixed,
+}
+
+impl BigComplex {
+    /// Parse a complex number in the form `'a + bi'`, where `a` and `b` are 
+    /// decimal numbers of any length, rounding each part to `bits` 
+    /// fractional bits
+    pub(crate) fn parse(s: &str, bits: u64) -> Result<Self, String> {
+        let compact: String = s.chars().filter(|ch| !ch.is_whitespace()).collect();
+
+        // The imaginary part starts at the last sign that isn't leading and 
+        // doesn't belong to an exponent
+        let bytes = compact.as_bytes();
+        let split = (1..bytes.len())
+            .rev()
+            .find(|&i| {
+                (bytes[i] == b'+' || bytes[i] == b'-')
+                    && !matches!(bytes[i - 1], b'e' | b'E')
+            });
+        let (re, im) = match (split, compact.strip_suffix('i')) {
+            (Some(i), Some(_)) => (&compact[..i], &compact[i..compact.len() - 1]),
+            (None, Some(im)) => ("0", im),
+            (_, None) => (compact.as_str(), "0"),
+        };
+        let im = match im {
+            "" | "+" => "1",
+            "-" => "-1",
+            im => im,
+        };
+        Ok(BigComplex {
+            re: BigFixed::parse(re, bits).map_err(|_| format!("invalid complex number `{}`", s))?,
+            im: BigFixed::parse(im, bits).map_err(|_| format!("invalid complex number `{}`", s))?,
+        })
+    }
+}
+
+impl fmt::Display for BigComplex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.im.mantissa.is_negative() { "" } else { "+" };
+        write!(f, "{}{}{}i", self.re, sign, self.im)
+    }
+}
+
+impl FromStr for BigComplex {
+    type Err = String;
+
+    /// Parse a complex number, keeping every digit supplied
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // log2(10) < 10/3, so this many bits represent every digit exactly 
+        // enough
+        let bits = 64 + (s.len() as u64 * 10) / 3;
+        BigComplex::parse(s, bits)
+    }
+}
+
+/// Return the number of fractional bits needed to resolve detail at the 
+/// scale of `pixel_size`
+pub(crate) fn precision_for(pixel_size: f64) -> u64 {
+    64 + (-pixel_size.log2()).max(0.0).ceil() as u64
+}
+
+/// The orbit of a single point, the reference, iterated in high precision 
+/// and rounded to double precision. The orbits of nearby points are computed 
+/// relative to it in double precision (perturbation theory).
+#[derive(Clone)]
+pub(crate) struct ReferenceOrbit {
+    orbit: Vec<Complex<f64>>,
+}
+
+impl ReferenceOrbit {
+    /// Iterate z ↦ z² + c from zero for the reference point `c` until it 
+    /// escapes the given radius or `num_iter` iterations have elapsed
+    pub(crate) fn new(c: &BigComplex, bits: u64, num_iter: usize, escape_radius: i64) -> Self {
+        let c = BigComplex {
+            re: BigFixed { mantissa: rescale(&c.re, bits), bits },
+            im: BigFixed { mantissa: rescale(&c.im, bits), bits },
+        };
+        let mut re = BigFixed::zero(bits);
+        let mut im = BigFixed::zero(bits);
+        let mut orbit = Vec::with_capacity(num_iter + 1);
+        orbit.push(Complex::new(0.0, 0.0));
+        for _ in 0..num_iter {
+            let re_sqr = re.mul(&re);
+            let im_sqr = im.mul(&im);
+            let re_im = re.mul(&im);
+            re = re_sqr.sub(&im_sqr).add(&c.re);
+            im = re_im.add(&re_im).add(&c.im);
+            orbit.push(Complex::new(re.to_f64(), im.to_f64()));
+            if re.is_greater_than_int(escape_radius) || im.is_greater_than_int(escape_radius) {
+                break;
+            }
+        }
+        ReferenceOrbit { orbit }
+    }
+
+    /// Iterate the point at offset `dc` from the reference point, tracking 
+    /// only the offset δ of its orbit from the reference orbit:
+    ///
+    /// δ ↦ 2Zδ + δ² + dc
+    ///
+    /// A glitch, where δ loses precision relative to the full value Z + δ, 
+    /// is detected when |Z + δ| < |δ|. The orbit is then rebased onto the 
+    /// start of the reference orbit by setting δ to Z + δ. The same rebasing 
+    /// happens when the reference orbit escapes before the point does.
+    pub(crate) fn iterate_delta(
+        &self,
+        dc: Complex<f64>,
+        num_iter: usize,
+        escape_radius_sqr: f64,
+        track_derivative: bool,
+    ) -> Orbit {
+        let last = self.orbit.len() - 1;
+        let mut delta = Complex::new(0.0, 0.0);
+        let mut dz = Complex::new(0.0, 0.0);
+        let mut min_norm_sqr = f64::INFINITY;
+        let mut m = 0;
+        for i in 0..num_iter {
+            let z = self.orbit[m] + delta;
+            let norm_sqr = z.norm_sqr();
+            if norm_sqr > escape_radius_sqr {
+                return Orbit::Escaped(Escape::new(i, z, dz));
+            }
+            if i > 0 {
+                min_norm_sqr = min_norm_sqr.min(norm_sqr);
+            }
+            if norm_sqr < delta.norm_sqr() || m == last {
+                delta = z;
+                m = 0;
+            }
+            if track_derivative {
+                dz = z * dz * 2.0 + Complex::one();
+            }
+            delta = (self.orbit[m] * 2.0 + delta) * delta + dc;
+            m += 1;
+        }
+        Orbit::Bounded(Interior::new(self.orbit[m] + delta, min_norm_sqr, 0))
+    }
+}
+
+/// Return the mantissa of `x` rescaled to `bits` fractional bits
+fn rescale(x: &BigFixed, bits: u64) -> BigInt {
+    if bits >= x.bits {
+        &x.mantissa << (bits - x.bits) as usize
+    } else {
+        &x.mantissa >> (x.bits - bits) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::Complex;
+
+    use crate::fractal::{Formula, Fractal, Orbit};
+    use crate::perturbation::{precision_for, BigComplex, BigFixed, ReferenceOrbit};
+
+    #[test]
+    fn big_fixed_parse_test() {
+        let bits = 80;
+        assert_eq!(BigFixed::parse("0.5", bits).unwrap().to_f64(), 0.5);
+        assert_eq!(BigFixed::parse("-1.25", bits).unwrap().to_f64(), -1.25);
+        assert_eq!(BigFixed::parse("+3", bits).unwrap().to_f64(), 3.0);
+        assert_eq!(BigFixed::parse("2.5e-3", bits).unwrap().to_f64(), 0.0025);
+        assert_eq!(BigFixed::parse("12E2", bits).unwrap().to_f64(), 1200.0);
+        assert!(BigFixed::parse("", bits).is_err());
+        assert!(BigFixed::parse("1.2.3", bits).is_err());
+        assert!(BigFixed::parse("0x10", bits).is_err());
+        assert!(BigFixed::parse("1e99999999999", bits).is_err());
+        assert!(BigFixed::parse("1e-5000000000", bits).is_err());
+        assert!(BigFixed::parse("1e-9223372036854775808", bits).is_err());
+
+        // Digits beyond double precision survive
+        let x = BigFixed::parse("1.00000000000000000000000001", 256).unwrap();
+        let y = BigFixed::parse("1", 256).unwrap();
+        assert!((x.sub(&y).to_f64() / 1e-26 - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn big_fixed_mul_test() {
+        let bits = 200;
+        let x = BigFixed::parse("-1.5", bits).unwrap();
+        let y = BigFixed::parse("0.25", bits).unwrap();
+        assert_eq!(x.mul(&y).to_f64(), -0.375);
+        assert!(x.is_greater_than_int(1));
+        assert!(!y.is_greater_than_int(1));
+    }
+
+    #[test]
+    fn big_complex_parse_test() {
+        let bits = 80;
+        let parse = |s| {
+            let z = BigComplex::parse(s, bits).unwrap();
+            Complex::new(z.re.to_f64(), z.im.to_f64())
+        };
+        assert_eq!(parse("-0.75 + 0.1i"), Complex::new(-0.75, 0.1));
+        assert_eq!(parse("1e-3-2E+1i"), Complex::new(0.001, -20.0));
+        assert_eq!(parse("-2"), Complex::new(-2.0, 0.0));
+        assert_eq!(parse("-0.5i"), Complex::new(0.0, -0.5));
+        assert_eq!(parse("1 - i"), Complex::new(1.0, -1.0));
+        assert!(BigComplex::parse("1 + xi", bits).is_err());
+    }
+
+    #[test]
+    fn big_complex_display_test() {
+        let bits = 80;
+        let z = BigComplex::parse("-0.75 - 1e-20i", bits).unwrap();
+        assert!(z.to_string().starts_with("-0.75000"));
+        assert_eq!(BigComplex::parse(&z.to_string(), bits).unwrap(), z);
+
+        let z = BigComplex::parse("0.1234567890123456789012345 + 2i", 256).unwrap();
+        assert_eq!(BigComplex::parse(&z.to_string(), 256).unwrap(), z);
+        assert_eq!(BigFixed::parse("-3", bits).unwrap().to_string().trim_end_matches('0'), "-3.");
+    }
+
+    #[test]
+    fn precision_for_test() {
+        assert_eq!(precision_for(1.0), 64);
+        assert_eq!(precision_for(2.0), 64);
+        assert_eq!(precision_for(1e-30), 64 + 100);
+    }
+
+    #[test]
+    fn iterate_delta_test() {
+        // At shallow depths, perturbation agrees with direct iteration
+        let center = "-0.75 + 0.1i";
+        let reference = ReferenceOrbit::new(&center.parse().unwrap(), 64, 1000, 256);
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None)
+            .without_interior_checks();
+        let c0 = Complex::new(-0.75, 0.1);
+        for k in 0..20 {
+            let dc = Complex::new(k as f64 * 1e-3, -(k as f64) * 5e-4);
+            let expected = fractal.iterate_point(c0 + dc, 1000).escape().map(|e| e.iter);
+            let actual = reference.iterate_delta(dc, 1000, 4.0, false).escape().map(|e| e.iter);
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn iterate_delta_rebase_test() {
+        // A reference outside the set escapes early; points inside the set 
+        // must keep iterating by rebasing
+        let reference = ReferenceOrbit::new(&"0.5".parse().unwrap(), 64, 1000, 256);
+        let result = reference.iterate_delta(Complex::new(-0.75, 0.0), 1000, 4.0, false);
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None)
+            .without_interior_checks();
+        match (result, fractal.iterate_point(Complex::new(-0.25, 0.0), 1000)) {
+            (Orbit::Bounded(actual), Orbit::Bounded(expected)) => {
+                assert!((actual.abs_z - expected.abs_z).abs() < 1e-12);
+                assert!((actual.min_abs_z - expected.min_abs_z).abs() < 1e-12);
+            }
+            _ => panic!("c = -0.25 doesn't escape"),
+        }
+        let result = reference.iterate_delta(Complex::new(0.5, 0.0), 1000, 4.0, false);
+        assert_eq!(result.escape().unwrap().iter, 3);
+    }
+}
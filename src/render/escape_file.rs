@@ -0,0 +1,347 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use num::Complex;
+
+use crate::fractal::{Escape, Exponent, Formula, Fractal, Interior, Orbit};
+use crate::perturbation::BigComplex;
+use crate::render::{EscapeBuffer, PixelSamples, Scene};
+use crate::view::{Projection, Sampling};
+
+/// Bytes that open every escape-data file
+const MAGIC: &[u8; 8] = b"FRACZESC";
+
+/// Version of the format written, which is the only version read
+const VERSION: u32 = 2;
+
+/// Length in bytes of the center, size and transformation matrix of the view
+const VIEW_LEN: u64 = 8 * 8;
+
+/// Bit set in the escape time recorded for samples that don't escape, whose 
+/// remaining bits hold the period of the orbit
+const NO_ESCAPE: u64 = 1 << 63;
+
+/// The outcome of every orbit iterated to draw an image, together with the 
+/// fractal and iteration limit they were computed with. The format is 
+/// described in docs/escape-data-format.md; the view it records isn't 
+/// decoded, since coloring doesn't need it.
+pub(crate) struct EscapeData {
+    pub(crate) fractal: Fractal,
+    pub(crate) max_iter: usize,
+    pub(crate) buffer: EscapeBuffer,
+}
+
+impl EscapeData {
+    /// Load escape data from a file
+    pub(crate) fn load(path: &Path) -> Result<EscapeData, io::Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        EscapeData::read(&mut reader).map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => invalid("truncated escape-data file"),
+            _ => err,
+        })
+    }
+
+    fn read<R: Read>(reader: &mut R) -> Result<EscapeData, io::Error> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not an escape-data file"));
+        }
+        let version = read_u32(reader)?;
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported escape-data version {}", version)));
+        }
+        let image_dims = (read_u32(reader)?, read_u32(reader)?);
+        let max_iter = usize::try_from(read_u64(reader)?)
+            .map_err(|_| invalid("iteration limit out of range"))?;
+
+        // Fractal
+        let formula = match read_u8(reader)? {
+            0 => Formula::Mandelbrot,
+            1 => Formula::Multibrot,
+            2 => Formula::BurningShip,
+            3 => Formula::Tricorn,
+            4 => Formula::Celtic,
+            code => return Err(invalid(&format!("unknown formula {}", code))),
+        };
+        let power = match (read_u8(reader)?, read_f64(reader)?) {
+            (0, d) if d.fract() == 0.0 && d > 1.0 && d <= u32::MAX as f64 => {
+                Exponent::Integer(d as u32)
+            }
+            (1, d) if d > 1.0 && d.is_finite() => Exponent::Real(d),
+            (0 | 1, d) => return Err(invalid(&format!("exponent {} out of range", d))),
+            (code, _) => return Err(invalid(&format!("unknown kind of power {}", code))),
+        };
+        let julia = match (read_u8(reader)?, read_complex(reader)?) {
+            (0, _) => None,
+            (_, c) => Some(c),
+        };
+        let bailout = read_f64(reader)?;
+        if !(bailout > 0.0 && bailout.is_finite()) {
+            return Err(invalid(&format!("bailout {} out of range", bailout)));
+        }
+        let flags = read_u8(reader)?;
+        let mut fractal = Fractal::new(formula, power, julia).with_escape_radius(bailout);
+        if flags & 1 != 0 {
+            fractal = fractal.with_derivative();
+        }
+        if flags & 2 == 0 {
+            fractal = fractal.without_cycle_detection();
+        }
+        if flags & 4 == 0 {
+            fractal = fractal.without_bulb_checks();
+        }
+
+        // View, whose center, size and transformation are skipped
+        skip(reader, VIEW_LEN)?;
+        let sampling = read_u8(reader)?;
+        if sampling > 1 {
+            return Err(invalid(&format!("unknown sampling {}", sampling)));
+        }
+        let projection = read_u8(reader)?;
+        if projection > 1 {
+            return Err(invalid(&format!("unknown projection {}", projection)));
+        }
+        let deep_center_len = read_u32(reader)?;
+        skip(reader, deep_center_len as u64)?;
+
+        // Samples, which are read before they're stored, so that a corrupt 
+        // header can't claim more memory than the file holds
+        let num_pixels = (image_dims.0 as usize)
+            .checked_mul(image_dims.1 as usize)
+            .ok_or_else(|| invalid("image dimensions out of range"))?;
+        let mut pixels = Vec::new();
+        for _ in 0..num_pixels {
+            let pixel_size = read_f64(reader)?;
+            let num_samples = read_u32(reader)?;
+            let orbits = (0..num_samples)
+                .map(|_| {
+                    let iter = read_u64(reader)?;
+                    let (abs_z, abs_dz_or_min) = (read_f64(reader)?, read_f64(reader)?);
+                    Ok(if iter & NO_ESCAPE == 0 {
+                        Orbit::Escaped(Escape { iter: iter as usize, abs_z, abs_dz: abs_dz_or_min })
+                    } else {
+                        let period = (iter & !NO_ESCAPE) as usize;
+                        Orbit::Bounded(Interior { abs_z, min_abs_z: abs_dz_or_min, period })
+                    })
+                })
+                .collect::<Result<_, io::Error>>()?;
+            pixels.push(PixelSamples { pixel_size, orbits });
+        }
+        let buffer = EscapeBuffer { dims: image_dims, rows: 0..image_dims.1, pixels };
+
+        Ok(EscapeData { fractal, max_iter, buffer })
+    }
+}
+
+/// Save the escape data of a whole image of a scene to a file
+pub(crate) fn save(
+    path: &Path,
+    scene: &Scene,
+    deep_center: Option<&BigComplex>,
+    buffer: &EscapeBuffer,
+) -> Result<(), io::Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer, scene, deep_center, buffer)?;
+    writer.flush()
+}
+
+fn write<W: Write>(
+    writer: &mut W,
+    scene: &Scene,
+    deep_center: Option<&BigComplex>,
+    buffer: &EscapeBuffer,
+) -> Result<(), io::Error> {
+    debug_assert!(buffer.rows == (0..buffer.dims.1));
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&buffer.dims.0.to_le_bytes())?;
+    writer.write_all(&buffer.dims.1.to_le_bytes())?;
+    writer.write_all(&(scene.max_iter as u64).to_le_bytes())?;
+
+    // Fractal
+    let fractal = scene.fractal;
+    let formula: u8 = match fractal.formula() {
+        Formula::Mandelbrot => 0,
+        Formula::Multibrot => 1,
+        Formula::BurningShip => 2,
+        Formula::Tricorn => 3,
+        Formula::Celtic => 4,
+    };
+    writer.write_all(&[formula])?;
+    let power = match fractal.power() {
+        Exponent::Integer(d) => (0, d as f64),
+        Exponent::Real(d) => (1, d),
+    };
+    writer.write_all(&[power.0])?;
+    writer.write_all(&power.1.to_le_bytes())?;
+    writer.write_all(&[fractal.julia().is_some() as u8])?;
+    write_complex(writer, fractal.julia().unwrap_or_default())?;
+    writer.write_all(&fractal.bailout().to_le_bytes())?;
+    let flags = fractal.tracks_derivative() as u8
+        | (fractal.detects_cycles() as u8) << 1
+        | (fractal.checks_bulbs() as u8) << 2;
+    writer.write_all(&[flags])?;
+
+    // View
+    let bounding_box = scene.bounding_box;
+    write_complex(writer, bounding_box.center())?;
+    writer.write_all(&bounding_box.dims().0.to_le_bytes())?;
+    writer.write_all(&bounding_box.dims().1.to_le_bytes())?;
+    for entry in bounding_box.transform().matrix().iter().flatten() {
+        writer.write_all(&entry.to_le_bytes())?;
+    }
+    let sampling: u8 = match bounding_box.sampling() {
+        Sampling::Center => 0,
+        Sampling::Corner => 1,
+    };
+    let projection: u8 = match bounding_box.projection() {
+        Projection::Linear => 0,
+        Projection::Exponential => 1,
+    };
+    writer.write_all(&[sampling, projection])?;
+    let deep_center = deep_center.map(|center| center.to_string()).unwrap_or_default();
+    writer.write_all(&(deep_center.len() as u32).to_le_bytes())?;
+    writer.write_all(deep_center.as_bytes())?;
+
+    // Samples
+    for pixel in buffer.pixels.iter() {
+        writer.write_all(&pixel.pixel_size.to_le_bytes())?;
+        writer.write_all(&(pixel.orbits.len() as u32).to_le_bytes())?;
+        for orbit in pixel.orbits.iter() {
+            let (iter, abs_z, abs_dz_or_min) = match *orbit {
+                Orbit::Escaped(escape) => (escape.iter as u64, escape.abs_z, escape.abs_dz),
+                Orbit::Bounded(interior) => {
+                    (NO_ESCAPE | interior.period as u64, interior.abs_z, interior.min_abs_z)
+                }
+            };
+            writer.write_all(&iter.to_le_bytes())?;
+            writer.write_all(&abs_z.to_le_bytes())?;
+            writer.write_all(&abs_dz_or_min.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Read past a number of bytes without keeping them
+fn skip<R: Read>(reader: &mut R, len: u64) -> Result<(), io::Error> {
+    if io::copy(&mut reader.take(len), &mut io::sink())? < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8, io::Error> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, io::Error> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, io::Error> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64<R: Read>(reader: &mut R) -> Result<f64, io::Error> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+fn read_complex<R: Read>(reader: &mut R) -> Result<Complex<f64>, io::Error> {
+    Ok(Complex::new(read_f64(reader)?, read_f64(reader)?))
+}
+
+fn write_complex<W: Write>(writer: &mut W, z: Complex<f64>) -> Result<(), io::Error> {
+    writer.write_all(&z.re.to_le_bytes())?;
+    writer.write_all(&z.im.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use num::Complex;
+
+    use crate::fractal::{Exponent, Formula, Fractal, Orbit};
+    use crate::perturbation::BigComplex;
+    use crate::render::escape_file::{write, EscapeData};
+    use crate::render::{EscapeBuffer, Scene};
+    use crate::view::{ComplexBoundingBox, Projection, Transform};
+
+    #[test]
+    fn escape_data_round_trip_test() {
+        let bounding_box = ComplexBoundingBox::from_center(Complex::new(-0.5, 0.25), 3.0, 1.5)
+            .with_transform(Transform::rotation(30.0))
+            .with_projection(Projection::Exponential);
+        let fractal = Fractal::new(Formula::Multibrot, Exponent::Real(2.5), None)
+            .with_escape_radius(256.0);
+        let scene = Scene { bounding_box: &bounding_box, fractal: &fractal, max_iter: 50 };
+        let buffer = EscapeBuffer::compute(&scene, (6, 4), 2);
+        let deep_center: BigComplex = "-1.25 + 0.000000000000000000001i".parse().unwrap();
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &scene, Some(&deep_center), &buffer).unwrap();
+        let data = EscapeData::read(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(data.buffer.dims, (6, 4));
+        assert_eq!(data.buffer.rows, 0..4);
+        assert_eq!(data.buffer.pixels, buffer.pixels);
+        let mut orbits = data.buffer.pixels.iter().flat_map(|p| p.orbits.iter());
+        assert!(orbits.any(|orbit| matches!(orbit, Orbit::Bounded(i) if i.period > 0)));
+        assert_eq!(data.max_iter, 50);
+        assert_eq!(data.fractal.formula(), Formula::Multibrot);
+        assert_eq!(data.fractal.power(), Exponent::Real(2.5));
+        assert_eq!(data.fractal.julia(), None);
+        assert_eq!(data.fractal.bailout(), 256.0);
+        assert!(!data.fractal.tracks_derivative());
+        assert!(data.fractal.detects_cycles());
+        assert!(!data.fractal.checks_bulbs()); // a Multibrot set
+
+        // Files that are truncated or from elsewhere are rejected
+        assert!(EscapeData::read(&mut &bytes[..bytes.len() - 1]).is_err());
+        assert!(EscapeData::read(&mut &b"PNG"[..]).is_err());
+        let mut future = bytes.clone();
+        future[8] = 3;
+        assert!(EscapeData::read(&mut future.as_slice()).is_err());
+
+        // Corrupt headers are rejected without allocating what they claim
+        let mut corrupt = bytes.clone();
+        corrupt[12..20].copy_from_slice(&[0xFF; 8]);
+        assert!(EscapeData::read(&mut corrupt.as_slice()).is_err());
+        let mut corrupt = bytes.clone();
+        corrupt[128] = 2;
+        assert!(EscapeData::read(&mut corrupt.as_slice()).is_err());
+        let mut corrupt = bytes.clone();
+        corrupt[29] = 0; // an integer power of 2.5
+        assert!(EscapeData::read(&mut corrupt.as_slice()).is_err());
+
+        // Powers are held to the same bounds as on the command line, and 
+        // bailouts must be positive and finite
+        let with_f64 = |range: std::ops::Range<usize>, value: f64| {
+            let mut corrupt = bytes.clone();
+            corrupt[range].copy_from_slice(&value.to_le_bytes());
+            EscapeData::read(&mut corrupt.as_slice())
+        };
+        assert_eq!(with_f64(30..38, 3.5).unwrap().fractal.power(), Exponent::Real(3.5));
+        assert!(with_f64(30..38, f64::NAN).is_err());
+        assert!(with_f64(30..38, 1.0).is_err());
+        assert_eq!(with_f64(55..63, 300.0).unwrap().fractal.bailout(), 300.0);
+        assert!(with_f64(55..63, -4.0).is_err());
+        assert!(with_f64(55..63, f64::INFINITY).is_err());
+        assert!(with_f64(55..63, f64::NAN).is_err());
+        let mut corrupt = bytes;
+        corrupt[130..134].copy_from_slice(&[0xFF; 4]);
+        assert!(EscapeData::read(&mut corrupt.as_slice()).is_err());
+    }
+}
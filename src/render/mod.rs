@@ -0,0 +1,897 @@
+pub(crate) mod escape_file;
+
+use std::ops::Range;
+
+use clap::ValueEnum;
+use image::{ImageBuffer, PixelWithColorType, Rgb, RgbImage, Rgba};
+use rayon::prelude::*;
+
+use crate::color::palettes::{PolarLuvPalette, Wrap};
+use crate::color::RGB;
+use crate::fractal::{Escape, Fractal, Interior, Orbit};
+use crate::view::ComplexBoundingBox;
+
+/// Strategy for turning the outcome of an orbit into a palette position
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Coloring {
+    /// Integer escape time
+    EscapeTime,
+    /// Normalized (continuous) iteration count
+    Smooth,
+    /// Exterior distance estimate, saturating at `thickness` pixels
+    Distance,
+    /// Crisp boundary `thickness` pixels wide drawn from the distance estimate
+    Boundary,
+}
+
+impl Coloring {
+    pub(crate) fn requires_derivative(&self) -> bool {
+        matches!(self, Coloring::Distance | Coloring::Boundary)
+    }
+}
+
+/// Strategy for coloring points that don't escape
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum InteriorColoring {
+    /// A single color
+    Solid,
+    /// Modulus of z after the last iteration
+    FinalModulus,
+    /// Period of the cycle the orbit settles into
+    Period,
+    /// Smallest modulus of z along the orbit
+    MinModulus,
+}
+
+impl InteriorColoring {
+    /// Return whether the coloring needs the orbits of interior points in 
+    /// full, which interior checks cut short
+    pub(crate) fn requires_full_orbit(&self) -> bool {
+        matches!(self, InteriorColoring::FinalModulus | InteriorColoring::MinModulus)
+    }
+}
+
+/// Strategy for turning escape times into palette positions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Mapping {
+    /// Proportional to the escape time
+    Linear,
+    /// Proportional to the logarithm of the escape time, which spreads out 
+    /// early escapes
+    Log,
+    /// Proportional to the square root of the escape time
+    Sqrt,
+    /// Proportional to the escape time raised to a power
+    Power,
+    /// Proportional to the share of the image's escaped samples that escaped 
+    /// sooner, so that the whole palette is used
+    Histogram,
+}
+
+/// Samples that are painted fully transparent, which leaves the pixels they 
+/// belong to partially transparent
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Transparency {
+    /// Whether points that don't escape are transparent
+    pub(crate) interior: bool,
+    /// Escape times, inclusive, whose points are transparent
+    pub(crate) escape_range: Option<(f64, f64)>,
+}
+
+impl Transparency {
+    /// Return whether any sample can be transparent
+    pub(crate) fn is_some(&self) -> bool {
+        self.interior || self.escape_range.is_some()
+    }
+}
+
+/// A type of pixel that can be painted from the mean color in linear light 
+/// of a pixel's opaque samples and the share of its samples that are opaque
+pub(crate) trait Paintable: PixelWithColorType + Send + Sync {
+    fn from_linear(color: RGB, alpha: f64) -> Self;
+}
+
+impl Paintable for Rgb<u8> {
+    fn from_linear(color: RGB, _alpha: f64) -> Self {
+        color.as_sRGB().as_image_Rgb()
+    }
+}
+
+impl Paintable for Rgba<u8> {
+    fn from_linear(color: RGB, alpha: f64) -> Self {
+        let Rgb([r, g, b]) = color.as_sRGB().as_image_Rgb();
+        Rgba([r, g, b, (alpha * 255.0).round() as u8])
+    }
+}
+
+impl Paintable for Rgb<u16> {
+    fn from_linear(color: RGB, _alpha: f64) -> Self {
+        color.as_sRGB().as_image_Rgb16()
+    }
+}
+
+impl Paintable for Rgba<u16> {
+    fn from_linear(color: RGB, alpha: f64) -> Self {
+        let Rgb([r, g, b]) = color.as_sRGB().as_image_Rgb16();
+        Rgba([r, g, b, (alpha * 65535.0).round() as u16])
+    }
+}
+
+impl Paintable for Rgb<f32> {
+    fn from_linear(color: RGB, _alpha: f64) -> Self {
+        color.as_image_Rgb32F()
+    }
+}
+
+/// Floating-point pixels are premultiplied by alpha, as OpenEXR expects
+impl Paintable for Rgba<f32> {
+    fn from_linear(color: RGB, alpha: f64) -> Self {
+        let Rgb([r, g, b]) = color.as_image_Rgb32F();
+        let alpha = alpha as f32;
+        Rgba([r * alpha, g * alpha, b * alpha, alpha])
+    }
+}
+
+/// A mapping from escape times to palette positions fitted to an image
+struct Scale {
+    /// Escape times mapped to the start and end of the palette, beyond 
+    /// which escape times are clamped
+    range: (f64, f64),
+    curve: Curve,
+}
+
+/// Shape of a mapping between the ends of its range
+enum Curve {
+    Linear,
+    Log,
+    Sqrt,
+    Power(f64),
+    /// Share of escaped samples whose clamped escape time rounds down to 
+    /// each whole number of iterations or fewer, counting from the start of 
+    /// the range rounded down, up to the latest escape
+    Histogram { cdf: Vec<f64> },
+}
+
+impl Scale {
+    /// Map an escape time, which may be fractional, to a palette position 
+    /// in [0.0, 1.0]
+    fn apply(&self, escape_time: f64) -> f64 {
+        let (min, max) = self.range;
+        let escape_time = escape_time.clamp(min, max);
+        let s = (escape_time - min) / (max - min);
+        match self.curve {
+            Curve::Linear => s,
+            Curve::Log => (escape_time - min).ln_1p() / (max - min).ln_1p(),
+            Curve::Sqrt => s.sqrt(),
+            Curve::Power(exponent) => s.powf(exponent),
+            Curve::Histogram { ref cdf } => {
+                // Interpolate linearly between whole escape times
+                let t = escape_time - min.floor();
+                let last = cdf.len() - 1;
+                let k = (t.floor() as usize).min(last);
+                let next = cdf[(k + 1).min(last)];
+                cdf[k] + (next - cdf[k]) * (t - k as f64)
+            }
+        }
+    }
+}
+
+/// A palette together with the strategy used to pick colors from it
+#[derive(Clone, Copy)]
+pub(crate) struct ColorMap<'a> {
+    pub(crate) palette: &'a PolarLuvPalette,
+    pub(crate) reverse: bool,
+    pub(crate) coloring: Coloring,
+    /// Mapping of escape times to palette positions, which applies only to 
+    /// the escape-time and smooth colorings
+    pub(crate) mapping: Mapping,
+    /// Exponent of the power mapping
+    pub(crate) exponent: f64,
+    /// Escape times mapped to the start and end of the palette, where the 
+    /// end defaults to the maximum number of iterations
+    pub(crate) escape_range: (f64, Option<f64>),
+    /// Width in pixels of the region colored by distance estimation
+    pub(crate) thickness: f64,
+    pub(crate) wrap: Wrap,
+    /// Number of times the palette spans the range of palette positions
+    pub(crate) repeats: f64,
+    /// Shift in palette position, used to cycle the palette
+    pub(crate) offset: f64,
+    pub(crate) interior: InteriorColoring,
+    /// Palette of the interior coloring, which spans it once
+    pub(crate) interior_palette: &'a PolarLuvPalette,
+    /// Color of points that don't escape under the solid interior coloring 
+    /// and of those whose period wasn't found under the period coloring
+    pub(crate) interior_color: RGB,
+    pub(crate) transparency: Transparency,
+}
+
+impl ColorMap<'_> {
+    /// Color a palette position in linear light
+    fn color(&self, scalar: f64) -> RGB {
+        let scalar = self.wrap.apply(scalar * self.repeats + self.offset);
+        self.palette.map_scalar_to_color(scalar, self.reverse).as_RGB()
+    }
+
+    /// Color a point that doesn't escape in linear light
+    fn color_interior(&self, fractal: &Fractal, interior: &Interior) -> RGB {
+        let scalar = match self.interior {
+            InteriorColoring::Solid => None,
+            InteriorColoring::FinalModulus => Some(interior.abs_z / fractal.interior_radius()),
+            InteriorColoring::Period => {
+                (interior.period > 0).then(|| 1.0 - (interior.period as f64).recip())
+            }
+            InteriorColoring::MinModulus => Some(interior.min_abs_z / fractal.interior_radius()),
+        };
+        match scalar {
+            Some(scalar) => {
+                self.interior_palette.map_scalar_to_color(scalar.min(1.0), false).as_RGB()
+            }
+            None => self.interior_color,
+        }
+    }
+
+    /// Return whether a sample is painted fully transparent
+    fn is_transparent(&self, fractal: &Fractal, orbit: &Orbit) -> bool {
+        match orbit {
+            Orbit::Escaped(escape) => self.transparency.escape_range.map_or(false, |(min, max)| {
+                let escape_time = self.escape_time(fractal, escape);
+                min <= escape_time && escape_time <= max
+            }),
+            Orbit::Bounded(_) => self.transparency.interior,
+        }
+    }
+
+    /// Color the opaque samples of a pixel and average the colors in linear 
+    /// light. Return the average and the share of samples that are opaque.
+    fn color_pixel(&self, fractal: &Fractal, scale: &Scale, pixel: &PixelSamples) -> (RGB, f64) {
+        let opaque = pixel.orbits.iter().filter(|orbit| !self.is_transparent(fractal, orbit));
+        let num_opaque = opaque.clone().count();
+        if num_opaque == 0 {
+            return (RGB::BLACK, 0.0);
+        }
+        let colors = opaque.map(|orbit| match orbit {
+            Orbit::Escaped(escape) => {
+                self.color(self.map_escape_to_scalar(fractal, escape, scale, pixel.pixel_size))
+            }
+            Orbit::Bounded(interior) => self.color_interior(fractal, interior),
+        });
+        (RGB::mean(colors), num_opaque as f64 / pixel.orbits.len() as f64)
+    }
+
+    /// Return the escape time of an escaped orbit, which is fractional in 
+    /// smooth coloring
+    fn escape_time(&self, fractal: &Fractal, escape: &Escape) -> f64 {
+        match self.coloring {
+            Coloring::Smooth => fractal.smooth_escape_time(escape),
+            _ => escape.iter as f64,
+        }
+    }
+
+    /// Fit the mapping of escape times to the escapes in a buffer
+    fn fit(&self, buffer: &EscapeBuffer, fractal: &Fractal, max_iter: usize) -> Scale {
+        let (min, max) = (self.escape_range.0, self.escape_range.1.unwrap_or(max_iter as f64));
+        let curve = match self.mapping {
+            Mapping::Linear => Curve::Linear,
+            Mapping::Log => Curve::Log,
+            Mapping::Sqrt => Curve::Sqrt,
+            Mapping::Power => Curve::Power(self.exponent),
+            Mapping::Histogram => {
+                // Bins span only the escape times seen, however high the 
+                // iteration limit
+                let bin = |escape: Escape| {
+                    (self.escape_time(fractal, &escape).clamp(min, max) - min.floor()) as usize
+                };
+                let escapes = || {
+                    let pixels = buffer.pixels.iter();
+                    pixels.flat_map(|pixel| pixel.orbits.iter().filter_map(Orbit::escape))
+                };
+                let latest = escapes().map(bin).max().unwrap_or(0);
+                let mut counts = vec![0_usize; latest.saturating_add(2)];
+                for k in escapes().map(bin) {
+                    counts[k] += 1;
+                }
+                let total = counts.iter().sum::<usize>().max(1) as f64;
+                let cdf = counts
+                    .iter()
+                    .scan(0, |cumulative, count| {
+                        *cumulative += count;
+                        Some(*cumulative as f64 / total)
+                    })
+                    .collect();
+                Curve::Histogram { cdf }
+            }
+        };
+        Scale { range: (min, max), curve }
+    }
+
+    /// Map an escaped orbit to a palette position, where positions in 
+    /// [0.0, 1.0] span the palette once
+    fn map_escape_to_scalar(
+        &self,
+        fractal: &Fractal,
+        escape: &Escape,
+        scale: &Scale,
+        pixel_size: f64,
+    ) -> f64 {
+        let width = self.thickness * pixel_size;
+        match self.coloring {
+            Coloring::EscapeTime | Coloring::Smooth => scale.apply(self.escape_time(fractal, escape)),
+            Coloring::Distance => (fractal.distance_estimate(escape) / width).tanh(),
+            Coloring::Boundary => {
+                if fractal.distance_estimate(escape) < width { 0.0 } else { 1.0 }
+            }
+        }
+    }
+}
+
+/// A fractal framed by a bounding box
+pub(crate) struct Scene<'a> {
+    pub(crate) bounding_box: &'a ComplexBoundingBox,
+    pub(crate) fractal: &'a Fractal,
+    pub(crate) max_iter: usize,
+}
+
+impl Scene<'_> {
+    /// Sample a pixel on a regular `samples`-by-`samples` grid
+    fn sample_pixel(&self, pixel: (u32, u32), image_dims: (u32, u32), samples: u32) -> PixelSamples {
+        let center = (pixel.0 as f64 + 0.5, pixel.1 as f64 + 0.5);
+        let pixel_size = self.bounding_box.pixel_size(center, image_dims);
+        let orbits = (0..samples * samples)
+            .map(|k| {
+                let sample = (k % samples, k / samples);
+                let point = self
+                    .bounding_box
+                    .map_sample_to_point(pixel, sample, samples, image_dims);
+                self.fractal.iterate_point(point, self.max_iter)
+            })
+            .collect();
+        PixelSamples { pixel_size, orbits }
+    }
+}
+
+/// The outcome of the orbit of each sample of a pixel
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PixelSamples {
+    /// Size of the pixel in the complex plane, which scales distance 
+    /// estimates
+    pub(crate) pixel_size: f64,
+    /// Orbit of each sample in row-major order
+    pub(crate) orbits: Vec<Orbit>,
+}
+
+/// Approximate number of samples to hold in memory at once when drawing an 
+/// image band by band
+const SAMPLES_PER_BAND: usize = 1 << 16;
+
+/// The outcome of every orbit iterated to draw a band of rows of an image, 
+/// which can be painted any number of times with different color maps 
+/// without iterating again
+pub(crate) struct EscapeBuffer {
+    pub(crate) dims: (u32, u32),
+    pub(crate) rows: Range<u32>,
+    /// Samples of each pixel in the band in row-major order
+    pub(crate) pixels: Vec<PixelSamples>,
+}
+
+impl EscapeBuffer {
+    /// Sample every pixel of a scene `samples`-by-`samples` times
+    pub(crate) fn compute(scene: &Scene, image_dims: (u32, u32), samples: u32) -> EscapeBuffer {
+        EscapeBuffer::compute_rows(scene, image_dims, 0..image_dims.1, samples)
+    }
+
+    /// Sample every pixel in a band of rows of a scene `samples`-by-`samples` 
+    /// times
+    fn compute_rows(
+        scene: &Scene,
+        image_dims: (u32, u32),
+        rows: Range<u32>,
+        samples: u32,
+    ) -> EscapeBuffer {
+        let width = image_dims.0 as usize;
+        let first = rows.start as usize * width;
+        let pixels = (first..rows.end as usize * width)
+            .into_par_iter()
+            .map(|i| {
+                let pixel = ((i % width) as u32, (i / width) as u32);
+                scene.sample_pixel(pixel, image_dims, samples)
+            })
+            .collect();
+        EscapeBuffer { dims: image_dims, rows, pixels }
+    }
+
+    /// Draw a scene band by band, so that only a band's worth of samples is 
+    /// held in memory at once, unless the color map is fitted to the whole 
+    /// image
+    pub(crate) fn draw<P>(
+        image: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+        scene: &Scene,
+        color_map: &ColorMap,
+        samples: u32,
+    ) where
+        P: Paintable,
+        P::Subpixel: Send,
+    {
+        let image_dims = image.dimensions();
+        if color_map.mapping == Mapping::Histogram {
+            EscapeBuffer::compute(scene, image_dims, samples)
+                .paint(image, scene.fractal, scene.max_iter, color_map);
+            return;
+        }
+        let samples_per_row = (image_dims.0 * samples * samples).max(1) as usize;
+        let band_height = (SAMPLES_PER_BAND / samples_per_row).max(1) as u32;
+        for start in (0..image_dims.1).step_by(band_height as usize) {
+            let rows = start..(start + band_height).min(image_dims.1);
+            EscapeBuffer::compute_rows(scene, image_dims, rows, samples)
+                .paint(image, scene.fractal, scene.max_iter, color_map);
+        }
+    }
+
+    /// Resample, `samples`-by-`samples` times, only those pixels that differ 
+    /// strongly from their neighbors in a painting of the buffer. Return the 
+    /// number of pixels refined.
+    pub(crate) fn refine(
+        &mut self,
+        painting: &RgbImage,
+        scene: &Scene,
+        samples: u32,
+        threshold: u8,
+    ) -> usize {
+        let image_dims = self.dims;
+        let width = image_dims.0 as usize;
+        let first = self.rows.start as usize * width;
+        self.pixels
+            .par_iter_mut()
+            .enumerate()
+            .map(|(i, samples_of_pixel)| {
+                let i = first + i;
+                let pixel = ((i % width) as u32, (i / width) as u32);
+                if differs_from_neighbors(painting, pixel, threshold) {
+                    *samples_of_pixel = scene.sample_pixel(pixel, image_dims, samples);
+                    1
+                } else {
+                    0
+                }
+            })
+            .sum()
+    }
+
+    /// Paint the buffer's band of an image with a color map fitted to the 
+    /// band
+    pub(crate) fn paint<P>(
+        &self,
+        image: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+        fractal: &Fractal,
+        max_iter: usize,
+        color_map: &ColorMap,
+    ) where
+        P: Paintable,
+        P::Subpixel: Send,
+    {
+        let channels = P::CHANNEL_COUNT as usize;
+        let row_len = channels * self.dims.0 as usize;
+        let band = self.rows.start as usize * row_len..self.rows.end as usize * row_len;
+        let scale = color_map.fit(self, fractal, max_iter);
+        (**image)[band]
+            .par_chunks_mut(channels)
+            .zip(self.pixels.par_iter())
+            .for_each(|(p, pixel)| {
+                let (color, alpha) = color_map.color_pixel(fractal, &scale, pixel);
+                *P::from_slice_mut(p) = P::from_linear(color, alpha);
+            });
+    }
+}
+
+/// Number of iterations beyond which the maximum number of iterations isn't 
+/// raised automatically
+const AUTO_ITER_LIMIT: usize = 1 << 18;
+
+/// Length in pixels beyond which the longer side of the preview drawn to 
+/// choose the maximum number of iterations is scaled down
+const PREVIEW_SIZE: u32 = 128;
+
+/// Choose a maximum number of iterations for a view of the fractal built by 
+/// `fractal_for` for a given number of iterations. Starting from an estimate 
+/// based on the height of the view, the number of iterations is doubled 
+/// until doing so lets fewer than 1 in 200 pixels of a low-resolution 
+/// preview escape that didn't before.
+pub(crate) fn choose_max_iter<F>(
+    bounding_box: &ComplexBoundingBox,
+    image_dims: (u32, u32),
+    fractal_for: F,
+) -> usize
+where
+    F: Fn(usize) -> Fractal,
+{
+    let scale = (PREVIEW_SIZE as f64 / image_dims.0.max(image_dims.1) as f64).min(1.0);
+    let preview_dims = (
+        ((image_dims.0 as f64 * scale).round() as u32).max(1),
+        ((image_dims.1 as f64 * scale).round() as u32).max(1),
+    );
+    let num_pixels = preview_dims.0 as usize * preview_dims.1 as usize;
+    let count_unescaped = |max_iter: usize| {
+        let fractal = fractal_for(max_iter);
+        let scene = Scene { bounding_box, fractal: &fractal, max_iter };
+        EscapeBuffer::compute(&scene, preview_dims, 1)
+            .pixels
+            .iter()
+            .filter(|pixel| pixel.orbits[0].escape().is_none())
+            .count()
+    };
+
+    let depth = (4.0 / bounding_box.dims().1).log2().max(1.0);
+    let mut max_iter = ((128.0 * depth) as usize).min(AUTO_ITER_LIMIT);
+    let mut unescaped = count_unescaped(max_iter);
+    while max_iter < AUTO_ITER_LIMIT {
+        let next = (2 * max_iter).min(AUTO_ITER_LIMIT);
+        let next_unescaped = count_unescaped(next);
+        if unescaped.saturating_sub(next_unescaped) <= num_pixels / 200 {
+            break;
+        }
+        max_iter = next;
+        unescaped = next_unescaped;
+    }
+    max_iter
+}
+
+/// Return whether any channel of any of the 8 neighbors of a pixel differs 
+/// from the pixel by more than `threshold`
+fn differs_from_neighbors(image: &RgbImage, pixel: (u32, u32), threshold: u8) -> bool {
+    let (x, y) = pixel;
+    let center = image.get_pixel(x, y);
+    let xs = x.saturating_sub(1)..=(x + 1).min(image.width() - 1);
+    let ys = y.saturating_sub(1)..=(y + 1).min(image.height() - 1);
+    ys.flat_map(|ny| xs.clone().map(move |nx| (nx, ny)))
+        .any(|(nx, ny)| {
+            image
+                .get_pixel(nx, ny)
+                .0
+                .iter()
+                .zip(center.0.iter())
+                .any(|(a, b)| a.abs_diff(*b) > threshold)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use image::{ImageBuffer, Rgb, RgbImage, Rgba, RgbaImage};
+    use num::Complex;
+
+    use crate::color::palettes::{PolarLuvPalette, Wrap};
+    use crate::color::{PolarLuv, RGB};
+    use crate::fractal::{Escape, Formula, Fractal, Interior, Orbit};
+    use crate::render::{
+        choose_max_iter, differs_from_neighbors, ColorMap, Coloring, Curve, EscapeBuffer,
+        InteriorColoring, Mapping, Paintable, PixelSamples, Scale, Scene, Transparency,
+        AUTO_ITER_LIMIT,
+    };
+    use crate::view::ComplexBoundingBox;
+
+    /// Load one of the palettes that ship with Fraczal
+    fn load_palette(name: &str) -> PolarLuvPalette {
+        let path = Path::new("assets/palettes").join(name).with_extension("json");
+        PolarLuvPalette::new(&path).unwrap()
+    }
+
+    #[test]
+    fn differs_from_neighbors_test() {
+        let mut image = RgbImage::from_pixel(4, 4, Rgb([100; 3]));
+        image.put_pixel(1, 1, Rgb([100, 100, 120]));
+
+        assert!(differs_from_neighbors(&image, (0, 0), 16));
+        assert!(differs_from_neighbors(&image, (1, 1), 16));
+        assert!(differs_from_neighbors(&image, (2, 2), 16));
+        assert!(!differs_from_neighbors(&image, (3, 3), 16));
+        assert!(!differs_from_neighbors(&image, (1, 1), 20));
+    }
+
+    #[test]
+    fn escape_buffer_test() {
+        let bounding_box = ComplexBoundingBox::from_center(Complex::new(-0.5, 0.0), 3.0, 1.5);
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None);
+        let scene = Scene { bounding_box: &bounding_box, fractal: &fractal, max_iter: 100 };
+        let image_dims = (6, 4);
+
+        let mut buffer = EscapeBuffer::compute(&scene, image_dims, 2);
+        assert_eq!(buffer.pixels.len(), 24);
+        assert!(buffer.pixels.iter().all(|pixel| pixel.orbits.len() == 4));
+        assert_eq!(buffer.pixels[7], scene.sample_pixel((1, 1), image_dims, 2));
+
+        // Only pixels that differ from their neighbors are resampled
+        let mut painting = RgbImage::from_pixel(6, 4, Rgb([0; 3]));
+        painting.put_pixel(5, 3, Rgb([255; 3]));
+        assert_eq!(buffer.refine(&painting, &scene, 3, 16), 4);
+        assert_eq!(buffer.pixels[0].orbits.len(), 4);
+        assert_eq!(buffer.pixels[23].orbits.len(), 9);
+    }
+
+    #[test]
+    fn paint_bands_test() {
+        let bounding_box = ComplexBoundingBox::from_center(Complex::new(-0.5, 0.0), 3.0, 1.5);
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None);
+        let scene = Scene { bounding_box: &bounding_box, fractal: &fractal, max_iter: 100 };
+        let palette = load_palette("Lajolla");
+        let color_map = ColorMap {
+            palette: &palette,
+            reverse: false,
+            coloring: Coloring::EscapeTime,
+            mapping: Mapping::Linear,
+            exponent: 1.0,
+            escape_range: (0.0, None),
+            thickness: 1.0,
+            wrap: Wrap::Clamp,
+            repeats: 1.0,
+            offset: 0.0,
+            interior: InteriorColoring::Solid,
+            interior_palette: &palette,
+            interior_color: RGB::BLACK,
+            transparency: Transparency::default(),
+        };
+        let image_dims = (12, 8);
+
+        let mut whole = RgbImage::new(12, 8);
+        EscapeBuffer::compute(&scene, image_dims, 2).paint(&mut whole, &fractal, 100, &color_map);
+
+        let mut banded = RgbImage::new(12, 8);
+        for rows in [0..3, 3..7, 7..8] {
+            EscapeBuffer::compute_rows(&scene, image_dims, rows, 2)
+                .paint(&mut banded, &fractal, 100, &color_map);
+        }
+        assert_eq!(whole, banded);
+
+        let mut drawn = RgbImage::new(12, 8);
+        EscapeBuffer::draw(&mut drawn, &scene, &color_map, 2);
+        assert_eq!(whole, drawn);
+    }
+
+    #[test]
+    fn scale_test() {
+        let scale = |curve| Scale { range: (0.0, 100.0), curve };
+        assert_eq!(scale(Curve::Linear).apply(25.0), 0.25);
+        assert_eq!(scale(Curve::Sqrt).apply(25.0), 0.5);
+        assert_eq!(scale(Curve::Power(2.0)).apply(50.0), 0.25);
+        assert_eq!(scale(Curve::Log).apply(0.0), 0.0);
+        assert_eq!(scale(Curve::Log).apply(100.0), 1.0);
+        assert!((scale(Curve::Log).apply(10.0) - 11.0_f64.ln() / 101.0_f64.ln()).abs() < 1e-15);
+
+        // Escape times outside the range are clamped
+        let scale = Scale { range: (20.0, 60.0), curve: Curve::Linear };
+        assert_eq!(scale.apply(10.0), 0.0);
+        assert_eq!(scale.apply(30.0), 0.25);
+        assert_eq!(scale.apply(90.0), 1.0);
+        let scale = Scale { range: (20.0, 60.0), curve: Curve::Log };
+        assert_eq!(scale.apply(10.0), 0.0);
+        assert_eq!(scale.apply(90.0), 1.0);
+    }
+
+    #[test]
+    fn histogram_mapping_test() {
+        let bounding_box = ComplexBoundingBox::from_center(Complex::new(-0.5, 0.0), 3.0, 1.5);
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None);
+        let scene = Scene { bounding_box: &bounding_box, fractal: &fractal, max_iter: 100 };
+        let palette = load_palette("Lajolla");
+        let color_map = ColorMap {
+            palette: &palette,
+            reverse: false,
+            coloring: Coloring::EscapeTime,
+            mapping: Mapping::Histogram,
+            exponent: 1.0,
+            escape_range: (0.0, None),
+            thickness: 1.0,
+            wrap: Wrap::Clamp,
+            repeats: 1.0,
+            offset: 0.0,
+            interior: InteriorColoring::Solid,
+            interior_palette: &palette,
+            interior_color: RGB::BLACK,
+            transparency: Transparency::default(),
+        };
+        let image_dims = (24, 16);
+        let buffer = EscapeBuffer::compute(&scene, image_dims, 2);
+
+        // The latest escape maps to the end of the palette and positions 
+        // grow with the escape time
+        let scale = color_map.fit(&buffer, &fractal, 100);
+        let escapes: Vec<_> =
+            buffer.pixels.iter().flat_map(|p| p.orbits.iter().filter_map(Orbit::escape)).collect();
+        let latest = escapes.iter().map(|escape| escape.iter).max().unwrap();
+        let earliest = escapes.iter().map(|escape| escape.iter).min().unwrap();
+        assert_eq!(scale.apply(latest as f64), 1.0);
+        assert!(scale.apply(earliest as f64) > 0.0);
+        assert!((0..=200).all(|k| scale.apply(k as f64 * 0.5) <= scale.apply(k as f64 * 0.5 + 0.25)));
+        let share = escapes.iter().filter(|escape| escape.iter <= 3).count() as f64;
+        assert_eq!(scale.apply(3.0), share / escapes.len() as f64);
+
+        // Escape times outside the range are clamped before they're counted
+        let scale = ColorMap { escape_range: (2.0, Some(10.0)), ..color_map }.fit(&buffer, &fractal, 100);
+        let share = escapes.iter().filter(|escape| escape.iter <= 2).count() as f64;
+        assert_eq!(scale.apply(0.0), share / escapes.len() as f64);
+        assert_eq!(scale.apply(50.0), 1.0);
+
+        // Bins don't depend on the iteration limit
+        let scale = color_map.fit(&buffer, &fractal, usize::MAX);
+        let share = escapes.iter().filter(|escape| escape.iter <= 3).count() as f64;
+        assert_eq!(scale.apply(3.0), share / escapes.len() as f64);
+        assert_eq!(scale.apply(latest as f64), 1.0);
+        assert_eq!(scale.apply(1e19), 1.0);
+
+        // The histogram is fitted to the whole image even when the image 
+        // would otherwise be drawn band by band
+        let bounding_box = ComplexBoundingBox::from_center(Complex::new(-0.5, 0.5), 3.0, 128.0);
+        let scene = Scene { bounding_box: &bounding_box, ..scene };
+        let image_dims = (256, 2);
+        let mut painted = RgbImage::new(256, 2);
+        EscapeBuffer::compute(&scene, image_dims, 16).paint(&mut painted, &fractal, 100, &color_map);
+        let mut drawn = RgbImage::new(256, 2);
+        EscapeBuffer::draw(&mut drawn, &scene, &color_map, 16);
+        assert_eq!(painted, drawn);
+    }
+
+    #[test]
+    fn color_interior_test() {
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None);
+        let palette = load_palette("Lajolla");
+        let interior_palette = load_palette("Mako");
+        let interior_color: PolarLuv = "120,30,90".parse().unwrap();
+        let color_map = ColorMap {
+            palette: &palette,
+            reverse: false,
+            coloring: Coloring::EscapeTime,
+            mapping: Mapping::Linear,
+            exponent: 1.0,
+            escape_range: (0.0, None),
+            thickness: 1.0,
+            wrap: Wrap::Clamp,
+            repeats: 1.0,
+            offset: 0.0,
+            interior: InteriorColoring::Solid,
+            interior_palette: &interior_palette,
+            interior_color: interior_color.as_RGB(),
+            transparency: Transparency::default(),
+        };
+        let color = |interior, abs_z, min_abs_z, period| {
+            let color_map = ColorMap { interior, ..color_map };
+            let interior = Interior { abs_z, min_abs_z, period };
+            color_map.color_interior(&fractal, &interior).as_sRGB().as_image_Rgb()
+        };
+        let palette_color =
+            |scalar| interior_palette.map_scalar_to_color(scalar, false).as_image_Rgb();
+
+        assert_eq!(color(InteriorColoring::Solid, 1.0, 0.5, 3), interior_color.as_image_Rgb());
+
+        // Moduli are measured against the radius bounded orbits stay within, 
+        // which is 2 for the Mandelbrot set
+        assert_eq!(color(InteriorColoring::FinalModulus, 1.0, 0.5, 3), palette_color(0.5));
+        assert_eq!(color(InteriorColoring::MinModulus, 1.0, 0.5, 3), palette_color(0.25));
+
+        // Periods start at the start of the palette and approach its end; 
+        // orbits with no known period get the interior color
+        assert_eq!(color(InteriorColoring::Period, 1.0, 0.5, 1), palette_color(0.0));
+        assert_eq!(color(InteriorColoring::Period, 1.0, 0.5, 4), palette_color(0.75));
+        assert_eq!(color(InteriorColoring::Period, 1.0, 0.5, 0), interior_color.as_image_Rgb());
+    }
+
+    #[test]
+    fn transparency_test() {
+        let bounding_box = ComplexBoundingBox::from_center(Complex::new(-0.5, 0.0), 3.0, 1.5);
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None);
+        let scene = Scene { bounding_box: &bounding_box, fractal: &fractal, max_iter: 100 };
+        let palette = load_palette("Lajolla");
+        let color_map = ColorMap {
+            palette: &palette,
+            reverse: false,
+            coloring: Coloring::EscapeTime,
+            mapping: Mapping::Linear,
+            exponent: 1.0,
+            escape_range: (0.0, None),
+            thickness: 1.0,
+            wrap: Wrap::Clamp,
+            repeats: 1.0,
+            offset: 0.0,
+            interior: InteriorColoring::Solid,
+            interior_palette: &palette,
+            interior_color: RGB::BLACK,
+            transparency: Transparency { interior: true, escape_range: None },
+        };
+        let buffer = EscapeBuffer::compute(&scene, (1, 1), 1);
+        let scale = color_map.fit(&buffer, &fractal, 100);
+        let escape = |iter| Orbit::Escaped(Escape { iter, abs_z: 4.0, abs_dz: 0.0 });
+        let pixel = PixelSamples {
+            pixel_size: 0.01,
+            orbits: vec![escape(5), escape(50), Orbit::Bounded(Interior::of_period(1)), escape(5)],
+        };
+        let paint = |transparency| {
+            let color_map = ColorMap { transparency, ..color_map };
+            let (color, alpha) = color_map.color_pixel(&fractal, &scale, &pixel);
+            Rgba::<u8>::from_linear(color, alpha)
+        };
+        let opaque = |escapes: &[Orbit]| {
+            let pixel = PixelSamples { pixel_size: 0.01, orbits: escapes.to_vec() };
+            let (color, _) = color_map.color_pixel(&fractal, &scale, &pixel);
+            let Rgb([r, g, b]) = Rgb::<u8>::from_linear(color, 1.0);
+            move |alpha| Rgba([r, g, b, alpha])
+        };
+
+        // Pixels are as transparent as the share of their samples that are
+        let transparency = Transparency { interior: true, escape_range: None };
+        assert_eq!(paint(transparency), opaque(&[escape(5), escape(50), escape(5)])(191));
+        let transparency = Transparency { interior: true, escape_range: Some((40.0, 60.0)) };
+        assert_eq!(paint(transparency), opaque(&[escape(5)])(128));
+        let transparency = Transparency { interior: true, escape_range: Some((0.0, 60.0)) };
+        assert_eq!(paint(transparency), Rgba([0; 4]));
+
+        // Floating-point pixels are premultiplied by alpha
+        let (color, alpha) = color_map.color_pixel(&fractal, &scale, &pixel);
+        let Rgb([r, g, b]) = Rgb::<f32>::from_linear(color, 1.0);
+        let premultiplied = Rgba([r * 0.75, g * 0.75, b * 0.75, 0.75]);
+        assert_eq!(Rgba::<f32>::from_linear(color, alpha), premultiplied);
+
+        // Points inside the set are transparent, while those that escape 
+        // right away are opaque
+        let mut image = RgbaImage::new(12, 8);
+        EscapeBuffer::draw(&mut image, &scene, &color_map, 1);
+        assert_eq!(image.get_pixel(4, 4).0[3], 0);
+        assert_eq!(image.get_pixel(0, 0).0[3], 255);
+    }
+
+    #[test]
+    fn paint_16_bit_test() {
+        let bounding_box = ComplexBoundingBox::from_center(Complex::new(-0.5, 0.0), 3.0, 1.5);
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None);
+        let scene = Scene { bounding_box: &bounding_box, fractal: &fractal, max_iter: 100 };
+        let palette = load_palette("Lajolla");
+        let color_map = ColorMap {
+            palette: &palette,
+            reverse: false,
+            coloring: Coloring::Smooth,
+            mapping: Mapping::Linear,
+            exponent: 1.0,
+            escape_range: (0.0, None),
+            thickness: 1.0,
+            wrap: Wrap::Clamp,
+            repeats: 1.0,
+            offset: 0.0,
+            interior: InteriorColoring::Solid,
+            interior_palette: &palette,
+            interior_color: RGB::BLACK,
+            transparency: Transparency { interior: true, escape_range: None },
+        };
+        let buffer = EscapeBuffer::compute(&scene, (12, 8), 1);
+        let mut image8 = RgbaImage::new(12, 8);
+        let mut image16 = ImageBuffer::<Rgba<u16>, Vec<u16>>::new(12, 8);
+        buffer.paint(&mut image8, &fractal, 100, &color_map);
+        buffer.paint(&mut image16, &fractal, 100, &color_map);
+
+        // 16-bit pixels refine, rather than change, 8-bit ones
+        for (p8, p16) in image8.pixels().zip(image16.pixels()) {
+            for (c8, c16) in p8.0.iter().zip(p16.0.iter()) {
+                assert!((*c8 as f64 - *c16 as f64 / 257.0).abs() <= 1.0);
+            }
+        }
+        assert_eq!(image16.get_pixel(4, 4).0[3], 0);
+        assert_eq!(image16.get_pixel(0, 0).0[3], 65535);
+    }
+
+    #[test]
+    fn choose_max_iter_test() {
+        let fractal = Fractal::new(Formula::Mandelbrot, Formula::MULTIBROT_POWER, None);
+        let choose = |center, complex_height| {
+            let bounding_box = ComplexBoundingBox::from_center(center, complex_height, 1.0);
+            choose_max_iter(&bounding_box, (32, 32), |_| fractal.clone())
+        };
+
+        // Deeper views near the boundary need more iterations
+        let shallow = choose(Complex::new(-0.5, 0.0), 4.0);
+        let deep = choose(Complex::new(-0.743643887, 0.131825904), 4e-6);
+        assert!(shallow <= 256);
+        assert!(deep > 4 * shallow);
+
+        // Views inside the set settle on the first estimate, since no more 
+        // of their pixels escape with more iterations
+        let estimate = (128.0 * (4.0_f64 / 0.01).log2()) as usize;
+        assert_eq!(choose(Complex::new(-0.1, 0.0), 0.01), estimate);
+        assert!(estimate < AUTO_ITER_LIMIT);
+    }
+}
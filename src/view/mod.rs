@@ -0,0 +1,407 @@
+use std::f64::consts::TAU;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use num::Complex;
+
+/// A linear transformation of the complex plane, represented as a 2-by-2 
+/// matrix acting on (Re z, Im z) column vectors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Transform {
+    matrix: [[f64; 2]; 2],
+}
+
+impl Transform {
+    pub(crate) const IDENTITY: Transform = Transform { matrix: [[1.0, 0.0], [0.0, 1.0]] };
+
+    /// Reflection that swaps left and right
+    pub(crate) const FLIP_HORIZONTAL: Transform = Transform { matrix: [[-1.0, 0.0], [0.0, 1.0]] };
+
+    /// Reflection that swaps top and bottom
+    pub(crate) const FLIP_VERTICAL: Transform = Transform { matrix: [[1.0, 0.0], [0.0, -1.0]] };
+
+    /// Counterclockwise rotation by an angle in degrees
+    pub(crate) fn rotation(degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Transform { matrix: [[cos, -sin], [sin, cos]] }
+    }
+
+    /// Horizontal shear that tilts vertical lines clockwise by an angle in 
+    /// degrees
+    pub(crate) fn skew(degrees: f64) -> Self {
+        Transform { matrix: [[1.0, degrees.to_radians().tan()], [0.0, 1.0]] }
+    }
+
+    /// Return the transformation that applies `self` and then `other`
+    pub(crate) fn then(&self, other: &Transform) -> Self {
+        let (a, b) = (&other.matrix, &self.matrix);
+        let mut matrix = [[0.0; 2]; 2];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, entry) in row.iter_mut().enumerate() {
+                *entry = a[i][0] * b[0][j] + a[i][1] * b[1][j];
+            }
+        }
+        Transform { matrix }
+    }
+
+    pub(crate) fn apply(&self, z: Complex<f64>) -> Complex<f64> {
+        let m = &self.matrix;
+        Complex::new(
+            m[0][0] * z.re + m[0][1] * z.im,
+            m[1][0] * z.re + m[1][1] * z.im,
+        )
+    }
+
+    /// Return the matrix in row-major order
+    pub(crate) fn matrix(&self) -> [[f64; 2]; 2] {
+        self.matrix
+    }
+
+    fn determinant(&self) -> f64 {
+        let m = &self.matrix;
+        m[0][0] * m[1][1] - m[0][1] * m[1][0]
+    }
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    /// Parse a matrix given in row-major order as `'a,b,c,d'`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected four comma-separated numbers, got `{}`", s);
+        let entries = s
+            .split(',')
+            .map(|entry| entry.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        match entries[..] {
+            [a, b, c, d] => {
+                let transform = Transform { matrix: [[a, b], [c, d]] };
+                if transform.determinant() == 0.0 {
+                    return Err(format!("matrix `{}` is singular", s));
+                }
+                Ok(transform)
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Where within each pixel, or each cell of a pixel's sampling grid, the 
+/// plane is sampled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Sampling {
+    /// Sample at the center of each cell, so that the samples of an image 
+    /// are spread symmetrically over the bounding box
+    Center,
+    /// Sample at the upper left corner of each cell, reproducing the 
+    /// renders of earlier versions of Fraczal
+    Corner,
+}
+
+impl Sampling {
+    /// Return the offset from a pixel's upper left corner, as a fraction of 
+    /// a pixel, of sample `k` of `n` along one axis
+    pub(crate) fn offset(&self, k: u32, n: u32) -> f64 {
+        match self {
+            Sampling::Center => (k as f64 + 0.5) / n as f64,
+            Sampling::Corner => k as f64 / n as f64,
+        }
+    }
+}
+
+/// How pixel coordinates are projected onto the complex plane
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Projection {
+    /// Pixels cover the bounding box evenly
+    Linear,
+    /// The horizontal axis is the angle around the center of the bounding 
+    /// box and the vertical axis is the logarithm of the distance from the 
+    /// center, which decreases downward from half the height of the box. 
+    /// Pixels are square, so each image width down is a zoom by e^(2π).
+    Exponential,
+}
+
+/// A bounding box in the complex plane defined by its upper left vertex, 
+/// width and height, and optionally rotated, skewed or flipped about its 
+/// center
+pub(crate) struct ComplexBoundingBox {
+    upper_left: Complex<f64>,
+    dims: (f64, f64),
+    transform: Transform,
+    sampling: Sampling,
+    projection: Projection,
+}
+
+impl ComplexBoundingBox {
+    pub(crate) fn new(
+        upper_left: Complex<f64>,
+        complex_height: f64,
+        aspect_ratio: f64,
+    ) -> Self {
+        ComplexBoundingBox {
+            upper_left,
+            dims: (complex_height * aspect_ratio, complex_height),
+            transform: Transform::IDENTITY,
+            sampling: Sampling::Center,
+            projection: Projection::Linear,
+        }
+    }
+
+    pub(crate) fn from_center(
+        center: Complex<f64>,
+        complex_height: f64,
+        aspect_ratio: f64,
+    ) -> Self {
+        let upper_left = center + Complex::new(-complex_height * aspect_ratio, complex_height) / 2.0;
+        Self::new(upper_left, complex_height, aspect_ratio)
+    }
+
+    /// Replace the transformation applied to the box about its center
+    pub(crate) fn with_transform(mut self, transform: Transform) -> Self {
+        let center = self.center();
+        self.upper_left = center - transform.apply(self.half_diagonal());
+        self.transform = transform;
+        self
+    }
+
+    pub(crate) fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    fn half_diagonal(&self) -> Complex<f64> {
+        Complex::new(self.dims.0, -self.dims.1) / 2.0
+    }
+
+    pub(crate) fn center(&self) -> Complex<f64> {
+        self.upper_left + self.transform.apply(self.half_diagonal())
+    }
+
+    /// Return the width and height of the box before it's transformed
+    pub(crate) fn dims(&self) -> (f64, f64) {
+        self.dims
+    }
+
+    pub(crate) fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub(crate) fn sampling(&self) -> Sampling {
+        self.sampling
+    }
+
+    pub(crate) fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Return the distance from the center of the box of points in a row of 
+    /// an exponential projection
+    fn exponential_radius(&self, row: f64, image_width: u32) -> f64 {
+        0.5 * self.dims.1 * (-TAU * row / image_width as f64).exp()
+    }
+
+    pub(crate) fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Return the distance in the complex plane between vertically adjacent 
+    /// pixels at a position in pixel coordinates, or the geometric mean of 
+    /// the pixel dimensions if the box is transformed
+    pub(crate) fn pixel_size(&self, pixel: (f64, f64), image_dims: (u32, u32)) -> f64 {
+        let size = match self.projection {
+            Projection::Linear => self.dims.1 / image_dims.1 as f64,
+            Projection::Exponential => {
+                self.exponential_radius(pixel.1, image_dims.0) * TAU / image_dims.0 as f64
+            }
+        };
+        size * self.transform.determinant().abs().sqrt()
+    }
+
+    /// Map sample `sample` of a `samples`-by-`samples` grid within a pixel 
+    /// to a point in the bounding box
+    pub(crate) fn map_sample_to_point(
+        &self,
+        pixel: (u32, u32),
+        sample: (u32, u32),
+        samples: u32,
+        image_dims: (u32, u32),
+    ) -> Complex<f64> {
+        let position = (
+            pixel.0 as f64 + self.sampling.offset(sample.0, samples),
+            pixel.1 as f64 + self.sampling.offset(sample.1, samples),
+        );
+        self.map_pixel_to_point(position, image_dims)
+    }
+
+    /// Map a position in pixel coordinates, in which the image spans 
+    /// [0, width] × [0, height] and integer positions are pixel corners, to 
+    /// a point in the bounding box
+    pub(crate) fn map_pixel_to_point(
+        &self,
+        pixel: (f64, f64),
+        image_dims: (u32, u32),
+    ) -> Complex<f64> {
+        match self.projection {
+            Projection::Linear => {
+                let displacement = Complex::new(
+                    pixel.0 * self.dims.0 / image_dims.0 as f64,
+                    -pixel.1 * self.dims.1 / image_dims.1 as f64,
+                );
+                self.upper_left + self.transform.apply(displacement)
+            }
+            Projection::Exponential => {
+                let radius = self.exponential_radius(pixel.1, image_dims.0);
+                let angle = TAU * pixel.0 / image_dims.0 as f64;
+                self.center() + self.transform.apply(Complex::from_polar(radius, angle))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::Complex;
+
+    use crate::view::{ComplexBoundingBox, Projection, Sampling, Transform};
+
+    fn approx_eq(a: Complex<f64>, b: Complex<f64>) -> bool {
+        (a - b).norm() < 1e-12
+    }
+
+    #[test]
+    fn map_pixel_to_point_test() {
+        let bounding_box = ComplexBoundingBox {
+            upper_left: Complex::<f64> { re: -1.0, im: 1.0 },
+            dims: (2.0, 2.0),
+            transform: Transform::IDENTITY,
+            sampling: Sampling::Center,
+            projection: Projection::Linear,
+        };
+        let image_dims = (100, 100);
+        assert_eq!(
+            bounding_box.map_pixel_to_point((0.0, 0.0), image_dims),
+            Complex::new(-1.0, 1.0),
+        );
+        assert_eq!(
+            bounding_box.map_pixel_to_point((100.0, 100.0), image_dims),
+            Complex::new(1.0, -1.0),
+        );
+        assert_eq!(
+            bounding_box.map_pixel_to_point((12.5, 50.0), image_dims),
+            Complex::new(-0.75, 0.0),
+        );
+    }
+
+    #[test]
+    fn map_sample_to_point_test() {
+        let bounding_box = ComplexBoundingBox::new(Complex::new(-1.0, 1.0), 2.0, 1.0);
+        let image_dims = (100, 100);
+
+        // The first and last pixels are sampled half a pixel in from the edges
+        assert_eq!(
+            bounding_box.map_sample_to_point((0, 0), (0, 0), 1, image_dims),
+            Complex::new(-0.99, 0.99),
+        );
+        assert_eq!(
+            bounding_box.map_sample_to_point((99, 99), (0, 0), 1, image_dims),
+            Complex::new(0.99, -0.99),
+        );
+        assert_eq!(
+            bounding_box.map_sample_to_point((0, 0), (1, 0), 2, image_dims),
+            Complex::new(-0.985, 0.995),
+        );
+
+        let bounding_box = bounding_box.with_sampling(Sampling::Corner);
+        assert_eq!(
+            bounding_box.map_sample_to_point((0, 0), (0, 0), 1, image_dims),
+            Complex::new(-1.0, 1.0),
+        );
+        assert_eq!(
+            bounding_box.map_sample_to_point((99, 99), (1, 1), 2, image_dims),
+            Complex::new(0.99, -0.99),
+        );
+    }
+
+    #[test]
+    fn from_center_test() {
+        let bounding_box = ComplexBoundingBox::from_center(Complex::new(-0.5, 0.25), 2.0, 1.5);
+        assert_eq!(bounding_box.upper_left, Complex::new(-2.0, 1.25));
+        assert_eq!(bounding_box.dims, (3.0, 2.0));
+    }
+
+    #[test]
+    fn with_transform_test() {
+        let image_dims = (100, 50);
+        let bounding_box = ComplexBoundingBox::from_center(Complex::new(1.0, 1.0), 2.0, 2.0)
+            .with_transform(Transform::rotation(90.0));
+
+        // The center stays put while the corners rotate about it
+        assert!(approx_eq(
+            bounding_box.map_pixel_to_point((50.0, 25.0), image_dims),
+            Complex::new(1.0, 1.0),
+        ));
+        assert!(approx_eq(
+            bounding_box.map_pixel_to_point((0.0, 0.0), image_dims),
+            Complex::new(0.0, -1.0),
+        ));
+        assert!(approx_eq(
+            bounding_box.map_pixel_to_point((100.0, 0.0), image_dims),
+            Complex::new(0.0, 3.0),
+        ));
+
+        // Replacing the transformation doesn't accumulate rotations
+        let bounding_box = bounding_box.with_transform(Transform::FLIP_HORIZONTAL);
+        assert!(approx_eq(
+            bounding_box.map_pixel_to_point((0.0, 0.0), image_dims),
+            Complex::new(3.0, 2.0),
+        ));
+        assert!((bounding_box.pixel_size((0.0, 0.0), image_dims) - 0.04).abs() < 1e-15);
+    }
+
+    #[test]
+    fn exponential_projection_test() {
+        let image_dims = (100, 300);
+        let bounding_box = ComplexBoundingBox::from_center(Complex::new(-1.0, 0.0), 2.0, 1.0)
+            .with_projection(Projection::Exponential);
+
+        // The top row is a circle of radius 1 around the center
+        assert!(approx_eq(
+            bounding_box.map_pixel_to_point((0.0, 0.0), image_dims),
+            Complex::new(0.0, 0.0),
+        ));
+        assert!(approx_eq(
+            bounding_box.map_pixel_to_point((25.0, 0.0), image_dims),
+            Complex::new(-1.0, 1.0),
+        ));
+
+        // Each image width down shrinks the radius by a factor of e^(2π)
+        let radius = (-std::f64::consts::TAU).exp();
+        assert!(approx_eq(
+            bounding_box.map_pixel_to_point((50.0, 100.0), image_dims),
+            Complex::new(-1.0 - radius, 0.0),
+        ));
+        let pixel_size = bounding_box.pixel_size((50.0, 100.0), image_dims);
+        assert!((pixel_size - radius * std::f64::consts::TAU / 100.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn transform_test() {
+        let z = Complex::new(1.0, 2.0);
+        assert!(approx_eq(Transform::rotation(90.0).apply(z), Complex::new(-2.0, 1.0)));
+        assert!(approx_eq(Transform::skew(45.0).apply(z), Complex::new(3.0, 2.0)));
+        assert_eq!(Transform::FLIP_VERTICAL.apply(z), Complex::new(1.0, -2.0));
+
+        let composed = Transform::FLIP_HORIZONTAL.then(&Transform::rotation(90.0));
+        assert!(approx_eq(composed.apply(z), Complex::new(-2.0, -1.0)));
+    }
+
+    #[test]
+    fn transform_from_str_test() {
+        assert_eq!("1, 0, 0, 1".parse::<Transform>(), Ok(Transform::IDENTITY));
+        assert!("1,2,2,4".parse::<Transform>().is_err());
+        assert!("1,0,0".parse::<Transform>().is_err());
+        assert!("1,0,0,x".parse::<Transform>().is_err());
+    }
+}